@@ -0,0 +1,576 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    config,
+    gpu::{Backend, PowerPreference},
+    renderer::{Palette, Shape},
+    rule::Rule,
+    simulation::{
+        Boundary, GridSize, Neighborhood, Settings, SimBackend, Symmetry, MAX_STATE_BUFFERS,
+    },
+    state::{Color, PresentMode},
+    timing::LateSteps,
+    ui::{MAX_STEPS_PER_TICK, MAX_UPDATE_INTERVAL, MIN_UPDATE_INTERVAL},
+};
+
+const DEFAULT_FRAMES: u32 = 100;
+const DEFAULT_WINDOW_SIZE: u32 = 512;
+const DEFAULT_HISTORY: usize = 0;
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+const DEFAULT_STEPS_PER_SUBMIT: u32 = 256;
+
+/// Command line options.
+pub struct Args {
+    /// Columns and rows of the grid, from `--grid WxH` or `--grid-size N`.
+    pub grid_size: GridSize,
+    /// Probability of a cell starting out alive.
+    pub density: f64,
+    /// Mirror the random grid across its middle column, or both middle lines, for
+    /// symmetric soups.
+    pub symmetric: Symmetry,
+    /// Seed for the initial random state, chosen at random when not given.
+    pub seed: Option<u64>,
+    /// Time between generations in a window.
+    pub update_interval: Duration,
+    /// Whether generations that came due during a slow frame are caught up on or skipped.
+    pub late_steps: LateSteps,
+    /// Show the Gosper glider gun, from `--demo`.
+    pub demo: bool,
+    /// Birth and survival rule of the automaton, and its number of states.
+    pub rule: Rule,
+    /// Second rule run side by side with `rule` from the same start, from `--compare`.
+    pub compare: Option<Rule>,
+    pub boundary: Boundary,
+    /// Neighbors counted by the rule, whose counts go up to the neighborhood size.
+    pub neighborhood: Neighborhood,
+    /// Whether the compute shader or the CPU reference computes the generations, see the
+    /// README for how fast each is.
+    pub sim_backend: SimBackend,
+    /// Color cells by how long they have been alive instead of by position.
+    pub color_by_age: bool,
+    pub shape: Shape,
+    /// Fade cells in and out between generations.
+    pub smooth: bool,
+    /// Fraction of each cell left empty as a border.
+    pub cell_gap: f32,
+    /// Draw dead cells too, so the gaps show up as lines between all cells.
+    pub grid_lines: bool,
+    /// Draw dead cells as faint ghosts of their live color, so the grid always shows.
+    pub show_dead: bool,
+    /// Highlight live cells on the edges of a wrapping grid.
+    pub show_wrap: bool,
+    /// Factor the afterimage of dead cells fades by each generation, 1 for no trail.
+    pub trail: f32,
+    /// Draw one pixel per cell and scale it up to the window, for sharp cell edges.
+    pub pixelated: bool,
+    /// Samples per pixel to smooth the edges of the cells with, 1 for none.
+    pub msaa: u32,
+    /// Draw the generation number in a corner, so it shows in recordings too.
+    pub show_generation: bool,
+    /// Keep the camera centered on the live cells as they move.
+    pub follow: bool,
+    /// Log when the grid settles into still lifes or a short cycle, which reads back every
+    /// generation.
+    pub detect_cycles: bool,
+    /// Re-seed the grid once it settles, implies `detect_cycles`.
+    pub auto_reset: bool,
+    /// Color behind the cells.
+    pub background: Color,
+    /// Colors of live cells at startup.
+    pub palette: Palette,
+    pub present_mode: PresentMode,
+    /// Most frames drawn per second in a window, 0 for uncapped.
+    pub max_fps: u32,
+    /// Inner size of the window in logical pixels, so it takes up the same part of a HiDPI
+    /// display. The grid is fit inside it.
+    pub window_width: u32,
+    pub window_height: u32,
+    /// Width and height of the compute shader workgroups.
+    pub workgroup_size: u32,
+    /// Number of cell state buffers the generations are written to in turn.
+    pub state_buffers: usize,
+    pub backend: Backend,
+    pub gpu: PowerPreference,
+    /// Stop advancing the simulation while the window doesn't have focus.
+    pub pause_on_blur: bool,
+    /// Run a throwaway step and frame before the first one, so the first frame doesn't wait
+    /// for the driver to compile the pipelines.
+    pub warmup: bool,
+    /// Log level from `--verbose` or `--quiet`, instead of `RUST_LOG`.
+    pub log_level: Option<log::LevelFilter>,
+    /// Also write the log to a file at this path, to keep a record of unattended runs.
+    pub log_file: Option<PathBuf>,
+    /// Run the simulation without opening a window.
+    pub headless: bool,
+    /// Time the generations in headless mode.
+    pub benchmark: bool,
+    /// Time a fixed set of grid and workgroup sizes in headless mode and print a table.
+    pub bench_matrix: bool,
+    /// Check every generation against the CPU reference in headless mode.
+    pub verify: bool,
+    /// Compare runs with fixed settings with the snapshots in `tests/goldens` in headless mode.
+    pub check_goldens: bool,
+    /// Set up the simulation and compile the shaders without running it, then print the
+    /// configuration, to catch bad flags and missing adapters before a long run.
+    pub check: bool,
+    /// Check that the frame and step pacing neither spins nor drifts, without a GPU.
+    pub check_timing: bool,
+    /// Run random soups from consecutive seeds in headless mode and print the seeds still
+    /// changing after `generations`.
+    pub soup_search: bool,
+    /// Number of soups run by `soup_search`, 100 when not given.
+    pub trials: Option<u64>,
+    /// Overwrite the golden snapshots instead of comparing with them.
+    pub bless: bool,
+    /// Write the final grid to `grid-{generation}.png` in headless mode.
+    pub export_grid: bool,
+    /// Most generations recorded into one command buffer, when more than one is due at once.
+    pub steps_per_submit: u32,
+    /// Generations run for each update interval in a window, and between the checks of `--verify`.
+    pub steps_per_tick: u32,
+    /// Number of generations to run before exiting, forever in a window when not given.
+    pub generations: Option<u64>,
+    /// Number of past generations kept to step back through, 4 bytes per cell each. None by
+    /// default, as keeping them reads every generation back and submits them one by one.
+    pub history: usize,
+    /// Record the first generations to a GIF at this path, then exit.
+    pub record: Option<PathBuf>,
+    /// Number of frames to record.
+    pub frames: u32,
+    /// Write the time of every frame, and of its compute pass when timestamp queries are
+    /// available, to a CSV file at this path.
+    pub timing_csv: Option<PathBuf>,
+    /// Start from a snapshot saved with W instead of a random grid.
+    pub load: Option<PathBuf>,
+    /// Start from an RLE or Life 1.06 pattern file, or a built-in pattern name, centered in
+    /// a dead grid.
+    pub pattern: Option<PathBuf>,
+    /// Cells from the top left corner of the grid to the top left corner of `pattern`, which
+    /// is centered when not given. Cells off the grid wrap around or are left out.
+    pub pattern_offset: Option<[isize; 2]>,
+    /// Patterns given as `--pattern NAME@X,Y`, each stamped into the same dead grid at its
+    /// offset as `pattern_offset` would place it.
+    pub placed_patterns: Vec<(PathBuf, [isize; 2])>,
+    /// Start from an image, where pixels darker than `image_threshold` are alive.
+    pub image: Option<PathBuf>,
+    /// Start from a centered square of this many live cells on a side and dead cells
+    /// around it. The density and seed don't apply.
+    pub seed_square: Option<usize>,
+    /// Brightness from 0 to 1 below which a pixel of `image` is a live cell.
+    pub image_threshold: f32,
+    /// List the adapters on every backend and exit.
+    pub print_adapters: bool,
+    /// List the built-in patterns with their sizes and exit.
+    pub list_patterns: bool,
+    /// Print the configuration as TOML, for use with `--config`, and exit.
+    pub dump_config: bool,
+    /// Reload the shaders from disk when they change.
+    #[cfg(feature = "hot-reload")]
+    pub watch: bool,
+    /// Problems with the options that don't stop the run, such as unknown keys in the
+    /// `--config` file, logged once the logger is set up.
+    pub warnings: Vec<String>,
+}
+
+impl Default for Args {
+    fn default() -> Args {
+        let defaults = Settings::default();
+        Args {
+            grid_size: defaults.grid_size,
+            density: defaults.density,
+            symmetric: Symmetry::default(),
+            seed: None,
+            update_interval: DEFAULT_UPDATE_INTERVAL,
+            late_steps: LateSteps::default(),
+            demo: false,
+            rule: Rule::default(),
+            compare: None,
+            boundary: Boundary::default(),
+            neighborhood: Neighborhood::default(),
+            sim_backend: SimBackend::default(),
+            color_by_age: false,
+            shape: Shape::default(),
+            smooth: false,
+            cell_gap: defaults.cell_gap,
+            grid_lines: false,
+            show_dead: false,
+            show_wrap: false,
+            follow: false,
+            detect_cycles: false,
+            auto_reset: false,
+            pixelated: false,
+            msaa: 1,
+            show_generation: false,
+            trail: defaults.trail,
+            background: Color::default(),
+            palette: Palette::default(),
+            present_mode: PresentMode::default(),
+            max_fps: 0,
+            window_width: DEFAULT_WINDOW_SIZE,
+            window_height: DEFAULT_WINDOW_SIZE,
+            workgroup_size: defaults.workgroup_size,
+            state_buffers: defaults.state_buffers,
+            backend: Backend::default(),
+            gpu: PowerPreference::default(),
+            pause_on_blur: true,
+            warmup: true,
+            log_level: None,
+            log_file: None,
+            headless: false,
+            benchmark: false,
+            bench_matrix: false,
+            verify: false,
+            check_goldens: false,
+            check: false,
+            check_timing: false,
+            soup_search: false,
+            trials: None,
+            bless: false,
+            export_grid: false,
+            generations: None,
+            steps_per_submit: DEFAULT_STEPS_PER_SUBMIT,
+            steps_per_tick: 1,
+            history: DEFAULT_HISTORY,
+            record: None,
+            frames: DEFAULT_FRAMES,
+            timing_csv: None,
+            load: None,
+            pattern: None,
+            pattern_offset: None,
+            placed_patterns: Vec::new(),
+            image: None,
+            seed_square: None,
+            image_threshold: 0.5,
+            print_adapters: false,
+            list_patterns: false,
+            dump_config: false,
+            #[cfg(feature = "hot-reload")]
+            watch: false,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+
+        // Applied after the loop, so it doesn't matter whether it comes before or after `--rule`.
+        let mut states = None;
+        let argv: Vec<String> = std::env::args().skip(1).collect();
+        // The file is applied first, so flags override it wherever they come.
+        if let Some(i) = argv.iter().position(|arg| arg == "--config") {
+            let path: PathBuf = parse_value("--config", argv.get(i + 1).cloned())?;
+            config::apply(&mut args, &path)?;
+        }
+
+        let mut iter = argv.into_iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    iter.next();
+                }
+                "--dump-config" => args.dump_config = true,
+                "--print-adapters" => args.print_adapters = true,
+                "--list-patterns" => args.list_patterns = true,
+                "--grid-size" | "--grid" => args.grid_size = parse_value(&arg, iter.next())?,
+                "--density" => args.density = parse_value(&arg, iter.next())?,
+                "--symmetric" => args.symmetric = parse_value(&arg, iter.next())?,
+                "--seed" => args.seed = Some(parse_value(&arg, iter.next())?),
+                // A starting point for new users, later flags still apply.
+                "--demo" => {
+                    args.demo = true;
+                    args.pattern = Some(PathBuf::from("gosper-gun"));
+                    args.grid_size = GridSize::square(64);
+                    args.update_interval = Duration::from_millis(300);
+                }
+                "--rule" => args.rule = parse_value(&arg, iter.next())?,
+                "--compare" => {
+                    args.rule = parse_value(&arg, iter.next())?;
+                    args.compare = Some(parse_value(&arg, iter.next())?);
+                }
+                "--states" => states = Some(parse_value(&arg, iter.next())?),
+                "--boundary" => args.boundary = parse_value(&arg, iter.next())?,
+                "--neighborhood" => args.neighborhood = parse_value(&arg, iter.next())?,
+                "--backend-sim" => args.sim_backend = parse_value(&arg, iter.next())?,
+                "--color-by-age" => args.color_by_age = true,
+                "--shape" => args.shape = parse_value(&arg, iter.next())?,
+                "--smooth" => args.smooth = true,
+                "--cell-gap" | "--gap" => args.cell_gap = parse_value(&arg, iter.next())?,
+                "--grid-lines" => args.grid_lines = true,
+                "--show-dead" => args.show_dead = true,
+                "--show-wrap" => args.show_wrap = true,
+                "--trail" => args.trail = parse_value(&arg, iter.next())?,
+                "--follow" => args.follow = true,
+                "--detect-cycles" => args.detect_cycles = true,
+                "--auto-reset" => {
+                    args.auto_reset = true;
+                    args.detect_cycles = true;
+                }
+                "--pixelated" => args.pixelated = true,
+                "--show-generation" => args.show_generation = true,
+                "--msaa" => args.msaa = parse_value(&arg, iter.next())?,
+                "--bg" => args.background = parse_value(&arg, iter.next())?,
+                "--palette" => args.palette = parse_value(&arg, iter.next())?,
+                "--present-mode" => args.present_mode = parse_value(&arg, iter.next())?,
+                "--max-fps" => args.max_fps = parse_value(&arg, iter.next())?,
+                "--late-steps" => args.late_steps = parse_value(&arg, iter.next())?,
+                "--window-width" => args.window_width = parse_value(&arg, iter.next())?,
+                "--window-height" => args.window_height = parse_value(&arg, iter.next())?,
+                "--update-interval" | "--interval-ms" => {
+                    args.update_interval = Duration::from_millis(parse_value(&arg, iter.next())?);
+                }
+                "--workgroup-size" | "--workgroup" => {
+                    args.workgroup_size = parse_value(&arg, iter.next())?
+                }
+                "--state-buffers" => args.state_buffers = parse_value(&arg, iter.next())?,
+                "--backend" => args.backend = parse_value(&arg, iter.next())?,
+                "--gpu" => args.gpu = parse_value(&arg, iter.next())?,
+                "--no-pause-on-blur" => args.pause_on_blur = false,
+                "--no-warmup" => args.warmup = false,
+                "-v" | "--verbose" => args.log_level = Some(log::LevelFilter::Debug),
+                "-q" | "--quiet" => args.log_level = Some(log::LevelFilter::Error),
+                "--log-file" => args.log_file = Some(parse_value(&arg, iter.next())?),
+                "--headless" => args.headless = true,
+                "--benchmark" => {
+                    args.benchmark = true;
+                    args.headless = true;
+                }
+                "--bench-matrix" => {
+                    args.bench_matrix = true;
+                    args.headless = true;
+                }
+                "--verify" => {
+                    args.verify = true;
+                    args.headless = true;
+                }
+                "--check-goldens" => {
+                    args.check_goldens = true;
+                    args.headless = true;
+                }
+                "--check" => {
+                    args.check = true;
+                    args.headless = true;
+                }
+                "--check-timing" => {
+                    args.check_timing = true;
+                    args.headless = true;
+                }
+                "--soup-search" => {
+                    args.soup_search = true;
+                    args.headless = true;
+                }
+                "--trials" => args.trials = Some(parse_value(&arg, iter.next())?),
+                "--bless" => args.bless = true,
+                "--export-grid" => args.export_grid = true,
+                "--generations" => args.generations = Some(parse_value(&arg, iter.next())?),
+                "--steps-per-submit" => args.steps_per_submit = parse_value(&arg, iter.next())?,
+                "--steps-per-tick" => args.steps_per_tick = parse_value(&arg, iter.next())?,
+                "--history" => args.history = parse_value(&arg, iter.next())?,
+                "--record" => args.record = Some(parse_value(&arg, iter.next())?),
+                "--frames" => args.frames = parse_value(&arg, iter.next())?,
+                "--timing-csv" => args.timing_csv = Some(parse_value(&arg, iter.next())?),
+                "--load" => args.load = Some(parse_value(&arg, iter.next())?),
+                "--pattern" => {
+                    let value: String = parse_value(&arg, iter.next())?;
+                    match value.rsplit_once('@') {
+                        Some((name, offset)) => {
+                            let offset = parse_offset(&arg, Some(offset.to_string()))?;
+                            args.placed_patterns.push((PathBuf::from(name), offset));
+                        }
+                        None => args.pattern = Some(PathBuf::from(value)),
+                    }
+                }
+                "--pattern-offset" => args.pattern_offset = Some(parse_offset(&arg, iter.next())?),
+                "--seed-square" => args.seed_square = Some(parse_value(&arg, iter.next())?),
+                "--image" => args.image = Some(parse_value(&arg, iter.next())?),
+                "--image-threshold" => args.image_threshold = parse_value(&arg, iter.next())?,
+                #[cfg(feature = "hot-reload")]
+                "--watch" => args.watch = true,
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        if let Some(states) = states {
+            if states < 2 {
+                return Err(format!("States must be at least 2, got {states}"));
+            }
+            args.rule.states = states;
+            if let Some(rule) = &mut args.compare {
+                rule.states = states;
+            }
+        }
+
+        if args.grid_size.width == 0 || args.grid_size.height == 0 {
+            return Err(format!(
+                "Grid size must be positive, got {}",
+                args.grid_size
+            ));
+        }
+
+        if let Some(side) = args.seed_square {
+            let grid_size = args.grid_size;
+            if side == 0 || side > grid_size.width || side > grid_size.height {
+                return Err(format!(
+                    "Seed square must be between 1 and {} cells on a side to fit the {grid_size} grid, got {side}",
+                    grid_size.width.min(grid_size.height)
+                ));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&args.density) {
+            return Err(format!(
+                "Density must be between 0.0 and 1.0, got {}",
+                args.density
+            ));
+        }
+
+        if !(0.0..1.0).contains(&args.cell_gap) {
+            return Err(format!(
+                "Cell gap must be at least 0.0 and below 1.0, got {}",
+                args.cell_gap
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&args.trail) {
+            return Err(format!(
+                "Trail decay must be between 0.0 and 1.0, got {}",
+                args.trail
+            ));
+        }
+
+        if args.window_width == 0 || args.window_height == 0 {
+            return Err("Window size must be positive".to_string());
+        }
+
+        if !matches!(args.msaa, 1 | 2 | 4) {
+            return Err(format!("MSAA must be 1, 2 or 4 samples, got {}", args.msaa));
+        }
+        if args.msaa > 1 && args.pixelated {
+            return Err("'--msaa' can't be combined with '--pixelated'".to_string());
+        }
+
+        if !(MIN_UPDATE_INTERVAL..=MAX_UPDATE_INTERVAL).contains(&args.update_interval) {
+            return Err(format!(
+                "Update interval must be between {} and {} ms, got {}",
+                MIN_UPDATE_INTERVAL.as_millis(),
+                MAX_UPDATE_INTERVAL.as_millis(),
+                args.update_interval.as_millis()
+            ));
+        }
+
+        if args.workgroup_size == 0 {
+            return Err("Workgroup size must be positive".to_string());
+        }
+
+        if !(2..=MAX_STATE_BUFFERS).contains(&args.state_buffers) {
+            return Err(format!(
+                "State buffers must be between 2 and {MAX_STATE_BUFFERS}, got {}",
+                args.state_buffers
+            ));
+        }
+
+        if args.steps_per_submit == 0 {
+            return Err("Steps per submit must be positive".to_string());
+        }
+
+        if !(1..=MAX_STEPS_PER_TICK).contains(&args.steps_per_tick) {
+            return Err(format!(
+                "Steps per tick must be between 1 and {MAX_STEPS_PER_TICK}, got {}",
+                args.steps_per_tick
+            ));
+        }
+
+        if args.frames == 0 {
+            return Err("Frames must be positive".to_string());
+        }
+
+        if args.bless && !args.check_goldens {
+            return Err("'--bless' only applies to '--check-goldens'".to_string());
+        }
+
+        if args.trials == Some(0) {
+            return Err("Trials must be positive".to_string());
+        }
+
+        if args.trials.is_some() && !args.soup_search {
+            return Err("'--trials' only applies to '--soup-search'".to_string());
+        }
+
+        if args.pattern_offset.is_some() && args.pattern.is_none() {
+            return Err("'--pattern-offset' only applies to '--pattern'".to_string());
+        }
+
+        if args.grid_lines && args.show_dead {
+            return Err("Only one of '--grid-lines' and '--show-dead' can be given".to_string());
+        }
+
+        if args.pattern.is_some() && !args.placed_patterns.is_empty() {
+            return Err(
+                "A '--pattern' without '@X,Y' can't be combined with placed patterns".to_string(),
+            );
+        }
+
+        let starts = [
+            args.load.is_some(),
+            args.pattern.is_some() || !args.placed_patterns.is_empty(),
+            args.image.is_some(),
+        ];
+        if starts.iter().filter(|&&start| start).count() > 1 {
+            return Err("Only one of '--load', '--pattern' and '--image' can be given".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&args.image_threshold) {
+            return Err(format!(
+                "Image threshold must be between 0.0 and 1.0, got {}",
+                args.image_threshold
+            ));
+        }
+
+        Ok(args)
+    }
+
+    /// The settings of the simulations to create.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            grid_size: self.grid_size,
+            boundary: self.boundary,
+            neighborhood: self.neighborhood,
+            sim_backend: self.sim_backend,
+            density: self.density,
+            symmetry: self.symmetric,
+            seed: self.seed,
+            workgroup_size: self.workgroup_size,
+            state_buffers: self.state_buffers,
+            color_by_age: self.color_by_age,
+            cell_gap: self.cell_gap,
+            grid_lines: self.grid_lines,
+            show_dead: self.show_dead,
+            shape: self.shape,
+            smooth: self.smooth,
+            palette: self.palette,
+            show_wrap: self.show_wrap,
+            trail: self.trail,
+        }
+    }
+}
+
+fn parse_value<T>(flag: &str, value: Option<String>) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| format!("Missing value for '{flag}'"))?;
+    value
+        .parse()
+        .map_err(|err| format!("Invalid value '{value}' for '{flag}': {err}"))
+}
+
+/// Parse an offset in cells given as `X,Y`, either of which can be negative.
+fn parse_offset(flag: &str, value: Option<String>) -> Result<[isize; 2], String> {
+    let value: String = parse_value(flag, value)?;
+    let offset = value
+        .split_once(',')
+        .and_then(|(x, y)| Some([x.trim().parse().ok()?, y.trim().parse().ok()?]));
+    offset.ok_or_else(|| format!("Invalid value '{value}' for '{flag}': expected X,Y like 4,-2"))
+}
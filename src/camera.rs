@@ -0,0 +1,92 @@
+//! Pan and zoom of the grid inside the window.
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 64.0;
+/// Fraction of the way to the followed point the camera moves each time, so it glides
+/// instead of jumping when the point does.
+const FOLLOW_EASING: f32 = 0.25;
+
+/// Maps the grid, which spans -1..1 in both directions, to normalized device
+/// coordinates as `pos * zoom * fit * shape + offset`, where `fit` shrinks the longer
+/// side of the window and `shape` the shorter side of the grid so cells stay square.
+pub struct Camera {
+    zoom: f32,
+    offset: [f32; 2],
+    fit: [f32; 2],
+    shape: [f32; 2],
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            zoom: 1.0,
+            offset: [0.0, 0.0],
+            fit: [1.0, 1.0],
+            shape: [1.0, 1.0],
+        }
+    }
+}
+
+impl Camera {
+    /// Fit the grid inside a window of the given size.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        let aspect = width as f32 / height as f32;
+        self.fit = if aspect > 1.0 {
+            [1.0 / aspect, 1.0]
+        } else {
+            [1.0, aspect]
+        };
+    }
+
+    /// Fit a grid of `width` x `height` cells, with its longer side across the window.
+    pub fn set_grid_size(&mut self, width: usize, height: usize) {
+        let longer = width.max(height) as f32;
+        self.shape = [width as f32 / longer, height as f32 / longer];
+    }
+
+    /// Scale of the grid in normalized device coordinates.
+    fn scale(&self) -> [f32; 2] {
+        [
+            self.zoom * self.fit[0] * self.shape[0],
+            self.zoom * self.fit[1] * self.shape[1],
+        ]
+    }
+
+    /// Scale and offset as passed to the shaders.
+    pub fn view(&self) -> [f32; 4] {
+        let [x, y] = self.scale();
+        [x, y, self.offset[0], self.offset[1]]
+    }
+
+    /// Zoom by `factor`, keeping the point under `anchor` in place.
+    pub fn zoom_at(&mut self, anchor: [f32; 2], factor: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let scale = zoom / self.zoom;
+        for (offset, anchor) in self.offset.iter_mut().zip(anchor) {
+            *offset = anchor - (anchor - *offset) * scale;
+        }
+        self.zoom = zoom;
+    }
+
+    /// Move the grid by `delta` in normalized device coordinates.
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        self.offset[0] += delta[0];
+        self.offset[1] += delta[1];
+    }
+
+    /// Move part of the way towards showing the grid position `target`, in -1..1, in the
+    /// center of the viewport.
+    pub fn follow(&mut self, target: [f32; 2]) {
+        let scale = self.scale();
+        for ((offset, target), scale) in self.offset.iter_mut().zip(target).zip(scale) {
+            let centered = -target * scale;
+            *offset += (centered - *offset) * FOLLOW_EASING;
+        }
+    }
+
+    /// The grid position, in -1..1, under a point in normalized device coordinates.
+    pub fn to_grid(&self, ndc: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.scale();
+        [(ndc[0] - self.offset[0]) / x, (ndc[1] - self.offset[1]) / y]
+    }
+}
@@ -0,0 +1,185 @@
+//! Options stored in a TOML file, loaded with `--config` and written with `--dump-config`.
+//!
+//! ```toml
+//! grid-size = 64 # or "64x16" for a grid that isn't square
+//! density = 0.6
+//! seed = 1
+//! rule = "B3/S23"
+//! boundary = "wrap"
+//! palette = "gradient"
+//! update-interval = 200 # milliseconds
+//! present-mode = "fifo"
+//! ```
+
+use std::{path::Path, time::Duration};
+
+use toml_edit::{Document, Value};
+
+use crate::{args::Args, simulation::GridSize};
+
+/// Set the options given in the file at `path`. Missing keys keep their
+/// defaults and unknown keys are skipped with a warning in `args.warnings`.
+pub fn apply(args: &mut Args, path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let document: Document = text
+        .parse()
+        .map_err(|err| format!("Invalid TOML in {}: {err}", path.display()))?;
+
+    for (key, item) in document.iter() {
+        let result = match item.as_value() {
+            Some(value) => apply_value(args, key, value),
+            None => Err("expected a value".to_string()),
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => args.warnings.push(format!(
+                "Ignoring unknown key '{key}' in {}",
+                path.display()
+            )),
+            Err(err) => {
+                return Err(format!(
+                    "Invalid value for '{key}' in {}: {err}",
+                    path.display()
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Set the option `key` to `value`, returning whether the key is known.
+fn apply_value(args: &mut Args, key: &str, value: &Value) -> Result<bool, String> {
+    match key {
+        "grid-size" => {
+            args.grid_size = match value {
+                Value::String(_) => string(value)?,
+                value => GridSize::square(integer(value)?),
+            }
+        }
+        "density" => {
+            args.density = match value {
+                Value::Integer(density) => *density.value() as f64,
+                value => value.as_float().ok_or("expected a number")?,
+            }
+        }
+        // TOML integers are signed, so seeds above `i64::MAX` are stored as negative numbers.
+        "seed" => args.seed = Some(integer::<i64>(value)? as u64),
+        "rule" => args.rule = string(value)?,
+        "boundary" => args.boundary = string(value)?,
+        "palette" => args.palette = string(value)?,
+        "update-interval" => args.update_interval = Duration::from_millis(integer(value)?),
+        "present-mode" => args.present_mode = string(value)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn integer<T: TryFrom<i64>>(value: &Value) -> Result<T, String> {
+    let integer = value.as_integer().ok_or("expected an integer")?;
+    T::try_from(integer).map_err(|_| format!("{integer} is out of range"))
+}
+
+fn string<T>(value: &Value) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let string = value.as_str().ok_or("expected a string")?;
+    string.parse().map_err(|err| format!("'{string}': {err}"))
+}
+
+/// The options that `apply` reads, as a TOML document.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn dump(args: &Args) -> String {
+    use toml_edit::value;
+
+    // The named options are parsed from their lowercase variant names.
+    let name = |option: &dyn std::fmt::Debug| format!("{option:?}").to_lowercase();
+
+    let mut document = Document::new();
+    document["grid-size"] = if args.grid_size.width == args.grid_size.height {
+        value(args.grid_size.width as i64)
+    } else {
+        value(args.grid_size.to_string())
+    };
+    document["density"] = value(args.density);
+    if let Some(seed) = args.seed {
+        document["seed"] = value(seed as i64);
+    }
+    document["rule"] = value(args.rule.to_string());
+    document["boundary"] = value(name(&args.boundary));
+    document["palette"] = value(name(&args.palette));
+    document["update-interval"] = value(args.update_interval.as_millis() as i64);
+    document["present-mode"] = value(name(&args.present_mode));
+    document.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{renderer::Palette, simulation::Boundary, state::PresentMode};
+
+    /// Apply `text` as a config file to the default options.
+    fn apply_text(name: &str, text: &str) -> Result<Args, String> {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("config-{}-{name}", std::process::id()));
+        std::fs::write(&path, text).expect("Failed to write the test file");
+        let mut args = Args::default();
+        let result = apply(&mut args, &path);
+        std::fs::remove_file(path).ok();
+        result.map(|()| args)
+    }
+
+    #[test]
+    fn dump_round_trips() {
+        let args = Args {
+            grid_size: "64x16".parse().unwrap(),
+            density: 0.25,
+            seed: Some(u64::MAX),
+            rule: "B36/S23".parse().unwrap(),
+            boundary: Boundary::Dead,
+            palette: Palette::Fire,
+            update_interval: Duration::from_millis(50),
+            present_mode: PresentMode::Immediate,
+            ..Args::default()
+        };
+
+        let loaded = apply_text("round-trip.toml", &dump(&args)).expect("Failed to apply the dump");
+        assert_eq!(loaded.grid_size, args.grid_size);
+        assert_eq!(loaded.density, args.density);
+        assert_eq!(loaded.seed, args.seed);
+        assert_eq!(loaded.rule, args.rule);
+        assert_eq!(loaded.boundary, args.boundary);
+        assert_eq!(loaded.palette, args.palette);
+        assert_eq!(loaded.update_interval, args.update_interval);
+        assert_eq!(loaded.present_mode, args.present_mode);
+        assert!(loaded.warnings.is_empty());
+    }
+
+    #[test]
+    fn missing_keys_keep_their_defaults() {
+        let args = apply_text("missing.toml", "density = 0.3\n").expect("Failed to apply");
+        let defaults = Args::default();
+        assert_eq!(args.density, 0.3);
+        assert_eq!(args.grid_size, defaults.grid_size);
+        assert_eq!(args.seed, None);
+        assert_eq!(args.rule, defaults.rule);
+        assert_eq!(args.update_interval, defaults.update_interval);
+    }
+
+    #[test]
+    fn unknown_keys_warn() {
+        let args =
+            apply_text("unknown.toml", "colour = \"red\"\nseed = 7\n").expect("Failed to apply");
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.warnings.len(), 1);
+        assert!(
+            args.warnings[0].contains("'colour'"),
+            "{}",
+            args.warnings[0]
+        );
+    }
+}
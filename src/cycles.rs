@@ -0,0 +1,57 @@
+//! Noticing when the grid has settled into still lifes or a short cycle, from hashes of
+//! its last few generations.
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+/// Longest period of the cycles that are noticed.
+pub const MAX_PERIOD: usize = 6;
+
+/// Hashes of the last generations, to find the next one among them.
+#[derive(Default)]
+pub struct CycleDetector {
+    // Oldest first.
+    recent: VecDeque<u64>,
+    settled: bool,
+}
+
+impl CycleDetector {
+    /// Add the next generation, returning the period of the cycle it closes if the grid
+    /// hadn't settled before. Still lifes and a dead grid have a period of 1.
+    pub fn push(&mut self, cells: &[u32]) -> Option<usize> {
+        let mut hasher = DefaultHasher::new();
+        cells.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let period = self
+            .recent
+            .iter()
+            .rev()
+            .position(|&recent| recent == hash)
+            .map(|i| i + 1);
+        if self.recent.len() == MAX_PERIOD {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(hash);
+        match period {
+            Some(period) if !self.settled => {
+                self.settled = true;
+                Some(period)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether one of the generations so far repeated an earlier one.
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Forget the generations so far, after the grid was changed.
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.settled = false;
+    }
+}
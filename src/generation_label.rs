@@ -0,0 +1,207 @@
+//! The generation number drawn over the grid in a corner of the window, from a small
+//! bitmap font, toggled with L. Unlike the title it shows up in screenshots and recordings.
+
+use wgpu::util::DeviceExt;
+
+/// The digits 0 to 9 of a 5x7 pixel font, one row per byte from the top, with the
+/// leftmost pixel in the highest of the five bits.
+const FONT: [[u8; GLYPH_HEIGHT]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+/// Most digits of a generation, those of `u64::MAX`.
+const MAX_DIGITS: usize = 20;
+/// Window pixels per pixel of the font.
+const SCALE: f32 = 3.0;
+/// Pixels between the label and the edges of the window.
+const MARGIN: f32 = 8.0;
+
+/// The pipeline that draws the label, and the uniform buffer holding its digits.
+pub struct GenerationLabel {
+    pipeline: wgpu::RenderPipeline,
+    label_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GenerationLabel {
+    /// A label drawn into textures of `format` with `sample_count` samples per pixel.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> GenerationLabel {
+        let source = include_str!("shaders/generation_label.wgsl")
+            .replace("${GLYPH_WIDTH}", &GLYPH_WIDTH.to_string())
+            .replace("${GLYPH_HEIGHT}", &GLYPH_HEIGHT.to_string())
+            .replace("${ADVANCE}", &(GLYPH_WIDTH + 1).to_string());
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Generation label shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // The glyphs side by side in one row, so digit `d` starts at texel `d * GLYPH_WIDTH`.
+        let mut texels = vec![0u8; FONT.len() * GLYPH_WIDTH * GLYPH_HEIGHT];
+        for (digit, glyph) in FONT.iter().enumerate() {
+            for (y, row) in glyph.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row >> (GLYPH_WIDTH - 1 - x) & 1 == 1 {
+                        texels[y * FONT.len() * GLYPH_WIDTH + digit * GLYPH_WIDTH + x] = 255;
+                    }
+                }
+            }
+        }
+        let atlas = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Generation label font"),
+                size: wgpu::Extent3d {
+                    width: (FONT.len() * GLYPH_WIDTH) as u32,
+                    height: GLYPH_HEIGHT as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            &texels,
+        );
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Generation label layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Generation label pipeline layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Generation label pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vertexMain",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fragmentMain",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        // The digits, their count and padding to the 96 bytes of the uniform struct.
+        let label_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Generation label"),
+            size: std::mem::size_of::<[u32; MAX_DIGITS + 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Generation label bind group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: label_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &atlas.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        GenerationLabel {
+            pipeline,
+            label_buffer,
+            bind_group,
+        }
+    }
+
+    /// Record drawing `generation` into the bottom left corner of the window of `width`
+    /// by `height` pixels. The digits are written to the queue, so they are up to date
+    /// once the pass is submitted.
+    pub fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        queue: &wgpu::Queue,
+        generation: u64,
+        [width, height]: [f32; 2],
+    ) {
+        let text = generation.to_string();
+        let mut data = [0u32; MAX_DIGITS + 4];
+        for (digit, c) in data.iter_mut().zip(text.bytes()) {
+            *digit = (c - b'0') as u32;
+        }
+        data[MAX_DIGITS] = text.len() as u32;
+        queue.write_buffer(&self.label_buffer, 0, bytemuck::cast_slice(&data));
+
+        let columns = text.len() * (GLYPH_WIDTH + 1) + 1;
+        let rows = GLYPH_HEIGHT + 2;
+        let size = [columns as f32 * SCALE, rows as f32 * SCALE];
+        // Too small a window to fit the label, which the viewport can't leave.
+        if size[0] + MARGIN > width || size[1] + MARGIN > height {
+            return;
+        }
+        pass.set_viewport(
+            MARGIN,
+            height - MARGIN - size[1],
+            size[0],
+            size[1],
+            0.0,
+            1.0,
+        );
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..6, 0..1);
+    }
+}
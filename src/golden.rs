@@ -0,0 +1,55 @@
+//! Runs that are compared cell for cell with the snapshots in `tests/goldens`, to catch
+//! changes in what the shaders compute, say after a wgpu upgrade.
+
+use std::path::PathBuf;
+
+use crate::simulation::{Boundary, GridSize, Neighborhood, Settings};
+
+/// Generations each golden run is advanced before it is compared.
+pub const GENERATIONS: u32 = 100;
+
+/// A run from a fixed random start with `rule` and `boundary`, kept as `tests/goldens/{name}.life`.
+pub struct Golden {
+    pub name: &'static str,
+    pub rule: &'static str,
+    pub boundary: Boundary,
+}
+
+pub const GOLDENS: [Golden; 3] = [
+    Golden {
+        name: "conway",
+        rule: "B3/S23",
+        boundary: Boundary::Wrap,
+    },
+    Golden {
+        name: "highlife-dead-edges",
+        rule: "B36/S23",
+        boundary: Boundary::Dead,
+    },
+    Golden {
+        name: "brians-brain",
+        rule: "B2/S/C3",
+        boundary: Boundary::Wrap,
+    },
+];
+
+impl Golden {
+    /// The grid and random start of the run, over the defaults.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            grid_size: GridSize::square(64),
+            boundary: self.boundary,
+            neighborhood: Neighborhood::Moore,
+            density: 0.5,
+            seed: Some(1),
+            ..Settings::default()
+        }
+    }
+
+    /// The snapshot of the last generation.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/goldens")
+            .join(format!("{}.life", self.name))
+    }
+}
@@ -0,0 +1,450 @@
+//! Helpers shared by the simulation and the renderer.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context};
+use instant::Instant;
+
+/// Graphics API used by wgpu, all of them unless one is forced with `--backend`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    All,
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
+}
+
+impl Backend {
+    fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::All => wgpu::Backends::all(),
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vulkan" => Ok(Backend::Vulkan),
+            "dx12" => Ok(Backend::Dx12),
+            "metal" => Ok(Backend::Metal),
+            "gl" => Ok(Backend::Gl),
+            _ => Err("expected 'vulkan', 'dx12', 'metal' or 'gl'".to_string()),
+        }
+    }
+}
+
+/// Which adapter to prefer when there are several, chosen with `--gpu`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerPreference {
+    /// Usually the integrated GPU.
+    #[default]
+    Low,
+    /// Usually the discrete GPU.
+    High,
+}
+
+impl From<PowerPreference> for wgpu::PowerPreference {
+    fn from(preference: PowerPreference) -> Self {
+        match preference {
+            PowerPreference::Low => wgpu::PowerPreference::LowPower,
+            PowerPreference::High => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl std::str::FromStr for PowerPreference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(PowerPreference::Low),
+            "high" => Ok(PowerPreference::High),
+            _ => Err("expected 'low' or 'high'".to_string()),
+        }
+    }
+}
+
+/// Create an instance limited to `backend`.
+pub fn create_instance(backend: Backend) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    })
+}
+
+/// Name, backend, type and driver of an adapter on one line.
+pub fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    let mut description = format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type);
+    if !info.driver.is_empty() {
+        description += &format!(", driver {} {}", info.driver, info.driver_info);
+    }
+    description
+}
+
+/// The limits that bound the grid and workgroup sizes on one line.
+pub fn describe_limits(limits: &wgpu::Limits) -> String {
+    format!(
+        "workgroups up to {}x{} with {} invocations, storage buffers up to {} MiB",
+        limits.max_compute_workgroup_size_x,
+        limits.max_compute_workgroup_size_y,
+        limits.max_compute_invocations_per_workgroup,
+        limits.max_storage_buffer_binding_size / (1024 * 1024),
+    )
+}
+
+/// Print every adapter on every backend, to choose from with `--backend` and `--gpu`.
+/// The browser only hands out the one adapter it picks.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn print_adapters() {
+    let instance = create_instance(Backend::All);
+    let mut found = false;
+    for adapter in instance.enumerate_adapters(wgpu::Backends::all()) {
+        println!("{}", describe_adapter(&adapter.get_info()));
+        println!("  {}", describe_limits(&adapter.limits()));
+        found = true;
+    }
+    if !found {
+        println!("No adapters found");
+    }
+}
+
+pub fn byte_length<T>(vec: &[T]) -> u64 {
+    std::mem::size_of_val(vec) as u64
+}
+
+/// Attempts at requesting an adapter or a device before giving up.
+const REQUEST_ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled for each one after that.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Run `request` until it succeeds, up to [`REQUEST_ATTEMPTS`] times. Flaky drivers, as
+/// on CI machines and in containers, sometimes fail to hand out an adapter or a device
+/// the first time.
+async fn retry<T, E: std::fmt::Display, F: std::future::Future<Output = Result<T, E>>>(
+    what: &str,
+    mut request: impl FnMut() -> F,
+) -> Result<T, E> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Err(err) if attempt < REQUEST_ATTEMPTS => {
+                log::warn!("Failed to {what} ({err}), retrying in {delay:?}");
+                // The browser can't block, there the retries follow each other right away.
+                #[cfg(not(target_arch = "wasm32"))]
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    options: &wgpu::RequestAdapterOptions<'_>,
+) -> Result<wgpu::Adapter, &'static str> {
+    instance
+        .request_adapter(options)
+        .await
+        .ok_or("no adapter found")
+}
+
+/// Request an adapter and a device. Without a `compatible_surface` any
+/// adapter will do, which is what the headless mode uses. When no adapter can draw to
+/// the surface, any adapter is requested instead so the error about the surface says
+/// which adapter was found.
+pub async fn request_device(
+    instance: &wgpu::Instance,
+    backend: Backend,
+    power_preference: PowerPreference,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> anyhow::Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    // ```js
+    // const adapter = await navigator.gpu.requestAdapter();
+    // if (!adapter) {
+    //     throw new Error("No appropriate GPUAdapter found.");
+    // }
+    // ```
+    let mut options = wgpu::RequestAdapterOptions {
+        power_preference: power_preference.into(),
+        compatible_surface,
+        ..Default::default()
+    };
+    let backends = match backend {
+        Backend::All => "any backend".to_string(),
+        backend => format!("the {backend:?} backend"),
+    };
+    let mut result = retry("request an adapter", || request_adapter(instance, &options)).await;
+    if result.is_err() && compatible_surface.is_some() {
+        options.compatible_surface = None;
+        result = retry("request an adapter", || request_adapter(instance, &options)).await;
+        if let Ok(adapter) = &result {
+            log::warn!(
+                "No adapter can draw to the window, found {} without it: rendering is unavailable",
+                adapter.get_info().name
+            );
+        }
+    }
+    let adapter = result.map_err(|_| {
+        let tried = match compatible_surface {
+            Some(_) => format!(
+                "{REQUEST_ATTEMPTS} times for one that can draw to the window and \
+                 {REQUEST_ATTEMPTS} times for any"
+            ),
+            None => format!("{REQUEST_ATTEMPTS} times"),
+        };
+        anyhow!("No appropriate adapter found for {backends}, tried {tried}")
+    })?;
+    let info = adapter.get_info();
+    log::info!("Using {} ({:?})", info.name, info.backend);
+
+    // ```js
+    // const device = await adapter.requestDevice();
+    // ```
+    // Timestamp queries are optional, they are only used to log GPU timings. So are the
+    // sample counts beyond those of WebGPU, for `--msaa 2`.
+    let features = adapter.features()
+        & (wgpu::Features::TIMESTAMP_QUERY
+            | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    if !features.contains(wgpu::Features::TIMESTAMP_QUERY) {
+        log::warn!("The adapter doesn't support timestamp queries, GPU timings are disabled");
+    }
+    let desc = wgpu::DeviceDescriptor {
+        features,
+        ..Default::default()
+    };
+    let (device, queue) = retry("request a device", || adapter.request_device(&desc, None))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to request a device from {}, tried {REQUEST_ATTEMPTS} times",
+                info.name
+            )
+        })?;
+
+    Ok((adapter, device, queue))
+}
+
+/// Map `buffer` for reading, wait for the GPU and copy out its content.
+pub fn read_buffer(device: &wgpu::Device, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Failed to map readback buffer")
+    });
+    device.poll(wgpu::Maintain::Wait);
+    let data = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    data
+}
+
+/// Copy the first `len` bytes of the cell state `storage` buffer, which needs
+/// `COPY_SRC`, into a new staging buffer and read them back as cells.
+pub async fn read_cell_state(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    storage: &wgpu::Buffer,
+    len: u64,
+) -> Vec<u32> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cell state staging"),
+        size: len,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(storage, 0, &staging, 0, len);
+    queue.submit(Some(encoder.finish()));
+
+    bytemuck::cast_slice(&read_buffer(device, &staging)).to_vec()
+}
+
+/// A buffer that is being mapped for reading without blocking on the GPU.
+pub struct PendingRead {
+    buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+}
+
+impl PendingRead {
+    pub fn new(buffer: wgpu::Buffer) -> PendingRead {
+        let mapped = Arc::new(AtomicBool::new(false));
+        let flag = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                result.expect("Failed to map readback buffer");
+                flag.store(true, Ordering::Release);
+            });
+        PendingRead { buffer, mapped }
+    }
+
+    /// The content of the buffer, once the GPU is done with it.
+    pub fn try_read(&self, device: &wgpu::Device) -> Option<Vec<u8>> {
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.load(Ordering::Acquire) {
+            return None;
+        }
+        let data = self.buffer.slice(..).get_mapped_range().to_vec();
+        self.buffer.unmap();
+        Some(data)
+    }
+}
+
+/// Whether textures of `format` can be drawn into with `sample_count` samples per pixel on
+/// `device`. Without adapter specific format features only the counts of WebGPU, 1 and 4,
+/// are allowed.
+pub fn supports_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> bool {
+    if device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        let features = adapter.get_texture_format_features(format);
+        features.flags.sample_count_supported(sample_count)
+    } else {
+        matches!(sample_count, 1 | 4)
+    }
+}
+
+/// Measures how long a stretch of GPU work takes with timestamp queries and
+/// logs the average once a second.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    // Readback buffer copied to by commands that may not have been submitted yet.
+    recorded: Option<wgpu::Buffer>,
+    pending: Option<PendingRead>,
+    // Nanoseconds per timestamp tick.
+    period: f64,
+    total: Duration,
+    samples: u32,
+    since: Instant,
+    // The last measurement, until it is taken for `--timing-csv`.
+    latest: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// A timer, or `None` when the device doesn't have timestamp queries enabled.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<GpuTimer> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp resolve"),
+            size: 2 * std::mem::size_of::<u64>() as u64,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        Some(GpuTimer {
+            query_set,
+            resolve_buffer,
+            recorded: None,
+            pending: None,
+            period: queue.get_timestamp_period() as f64,
+            total: Duration::ZERO,
+            samples: 0,
+            since: Instant::now(),
+            latest: None,
+        })
+    }
+
+    /// Write the timestamp before the measured work.
+    pub fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 0);
+    }
+
+    /// Write the timestamp after the measured work and start reading both back,
+    /// unless the previous measurement is still on its way.
+    pub fn end(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 1);
+        if self.recorded.is_some() || self.pending.is_some() {
+            return;
+        }
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp readback"),
+            size: self.resolve_buffer.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &buffer, 0, buffer.size());
+        self.recorded = Some(buffer);
+    }
+
+    /// The measurement collected since this was last called, if any.
+    pub fn take_latest(&mut self) -> Option<Duration> {
+        self.latest.take()
+    }
+
+    /// Collect a finished measurement, to be called after the work from `end` was submitted.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if let Some(buffer) = self.recorded.take() {
+            self.pending = Some(PendingRead::new(buffer));
+        }
+        let Some(data) = self
+            .pending
+            .as_ref()
+            .and_then(|pending| pending.try_read(device))
+        else {
+            return;
+        };
+        self.pending = None;
+
+        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+        let ticks = timestamps[1].saturating_sub(timestamps[0]);
+        let time = Duration::from_nanos((ticks as f64 * self.period) as u64);
+        self.total += time;
+        self.samples += 1;
+        self.latest = Some(time);
+
+        if self.since.elapsed() >= Duration::from_secs(1) {
+            log::info!("GPU compute time {:?}", self.total / self.samples);
+            self.total = Duration::ZERO;
+            self.samples = 0;
+            self.since = Instant::now();
+        }
+    }
+}
+
+/// Run `create` while capturing validation errors, instead of letting the
+/// default error handler panic.
+#[cfg(feature = "hot-reload")]
+pub fn with_validation<T>(
+    device: &wgpu::Device,
+    create: impl FnOnce() -> T,
+) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err),
+        None => Ok(value),
+    }
+}
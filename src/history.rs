@@ -0,0 +1,62 @@
+//! Past generations kept on the CPU to step the simulation backwards.
+
+use std::collections::VecDeque;
+
+/// A generation number together with its cells.
+pub type Snapshot = (u64, Vec<u32>);
+
+/// Generations before the displayed one, and after it once stepped back.
+pub struct History {
+    depth: usize,
+    past: VecDeque<Snapshot>,
+    future: Vec<Snapshot>,
+}
+
+impl History {
+    /// Keep up to `depth` generations, each costing 4 bytes per cell.
+    pub fn new(depth: usize) -> History {
+        History {
+            depth,
+            past: VecDeque::with_capacity(depth),
+            future: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Remember the displayed generation before the simulation computes a new one.
+    /// Whatever was ahead of it is computed again.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        self.future.clear();
+        if self.past.len() == self.depth {
+            self.past.pop_front();
+        }
+        self.past.push_back(snapshot);
+    }
+
+    /// Swap the displayed generation `current` for the one before it.
+    pub fn back(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous = self.past.pop_back()?;
+        self.future.push(current);
+        Some(previous)
+    }
+
+    /// Swap the displayed generation `current` for the one after it, if it was stepped back from.
+    pub fn forward(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.future.pop()?;
+        self.past.push_back(current);
+        Some(next)
+    }
+
+    /// Forget the generations ahead of the displayed one, after it was edited.
+    pub fn truncate_future(&mut self) {
+        self.future.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+    }
+}
@@ -0,0 +1,264 @@
+//! The keyboard controls, listed once for both the event loop and the help overlay.
+
+use winit::event::VirtualKeyCode as Key;
+
+/// What a key press does, carried out by the event loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Step,
+    StepBack,
+    StepForward,
+    SpeedUp,
+    SlowDown,
+    MoreStepsPerTick,
+    FewerStepsPerTick,
+    Screenshot,
+    ExportGrid,
+    SaveGrid,
+    Reseed,
+    Clear,
+    Fill,
+    BrushSize,
+    CyclePalette,
+    CycleGradient,
+    CycleRule,
+    PopulationGraph,
+    Heatmap,
+    Invert,
+    HighContrast,
+    Minimap,
+    GenerationLabel,
+    Rendering,
+    Fullscreen,
+    CopyPattern,
+    PastePattern,
+    GrowGrid,
+    ShrinkGrid,
+    Help,
+}
+
+impl Command {
+    /// Whether the command reads the grid back from the GPU and waits for it, which the
+    /// browser can't: there buffers are only mapped once control returns to the page.
+    pub fn reads_back(self) -> bool {
+        matches!(
+            self,
+            Command::StepBack
+                | Command::StepForward
+                | Command::Screenshot
+                | Command::ExportGrid
+                | Command::CycleRule
+                | Command::CopyPattern
+                | Command::GrowGrid
+                | Command::ShrinkGrid
+        )
+    }
+}
+
+/// The keys of a command and how the help overlay describes them.
+pub struct Binding {
+    pub keys: &'static [Key],
+    /// Only with Shift held, otherwise Shift doesn't matter.
+    pub shift: bool,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub command: Command,
+}
+
+const fn bind(
+    keys: &'static [Key],
+    label: &'static str,
+    description: &'static str,
+    command: Command,
+) -> Binding {
+    Binding {
+        keys,
+        shift: false,
+        label,
+        description,
+        command,
+    }
+}
+
+pub const BINDINGS: &[Binding] = &[
+    bind(&[Key::F1], "F1", "Show or hide this help", Command::Help),
+    bind(&[Key::Space], "Space", "Pause or resume", Command::Pause),
+    bind(
+        &[Key::S],
+        "S",
+        "Step one generation while paused",
+        Command::Step,
+    ),
+    bind(
+        &[Key::Left],
+        "Left",
+        "Step back through the --history while paused",
+        Command::StepBack,
+    ),
+    bind(
+        &[Key::Right, Key::Period],
+        "Right or .",
+        "Step forward while paused",
+        Command::StepForward,
+    ),
+    bind(
+        &[Key::Plus, Key::Equals, Key::NumpadAdd],
+        "+",
+        "Speed up",
+        Command::SpeedUp,
+    ),
+    bind(
+        &[Key::Minus, Key::NumpadSubtract],
+        "-",
+        "Slow down",
+        Command::SlowDown,
+    ),
+    bind(
+        &[Key::RBracket],
+        "]",
+        "Run more generations per tick",
+        Command::MoreStepsPerTick,
+    ),
+    bind(
+        &[Key::LBracket],
+        "[",
+        "Run fewer generations per tick",
+        Command::FewerStepsPerTick,
+    ),
+    bind(&[Key::R], "R", "Re-seed with random cells", Command::Reseed),
+    bind(&[Key::Delete], "Delete", "Clear the grid", Command::Clear),
+    Binding {
+        shift: true,
+        ..bind(
+            &[Key::R],
+            "Shift+R",
+            "Clear the grid to draw on",
+            Command::Clear,
+        )
+    },
+    bind(&[Key::F], "F", "Fill the grid", Command::Fill),
+    bind(
+        &[
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::Key5,
+            Key::Key6,
+            Key::Key7,
+            Key::Key8,
+            Key::Key9,
+        ],
+        "1-9",
+        "Brush size in cells",
+        Command::BrushSize,
+    ),
+    bind(&[Key::C], "C", "Cycle the palette", Command::CyclePalette),
+    bind(&[Key::V], "V", "Cycle the gradient", Command::CycleGradient),
+    bind(
+        &[Key::U],
+        "U",
+        "Switch to the next preset rule",
+        Command::CycleRule,
+    ),
+    bind(
+        &[Key::G],
+        "G",
+        "Show or hide the population graph",
+        Command::PopulationGraph,
+    ),
+    bind(
+        &[Key::N],
+        "N",
+        "Show or hide the neighbor heatmap",
+        Command::Heatmap,
+    ),
+    bind(&[Key::X], "X", "Invert the colors", Command::Invert),
+    bind(
+        &[Key::H],
+        "H",
+        "Toggle high contrast, white cells on black",
+        Command::HighContrast,
+    ),
+    bind(&[Key::M], "M", "Show or hide the minimap", Command::Minimap),
+    bind(
+        &[Key::L],
+        "L",
+        "Show or hide the generation number",
+        Command::GenerationLabel,
+    ),
+    bind(
+        &[Key::O],
+        "O",
+        "Stop or resume drawing, the simulation keeps running",
+        Command::Rendering,
+    ),
+    bind(&[Key::F11], "F11", "Toggle fullscreen", Command::Fullscreen),
+    bind(
+        &[Key::PageUp],
+        "Page Up",
+        "Double the grid",
+        Command::GrowGrid,
+    ),
+    bind(
+        &[Key::PageDown],
+        "Page Down",
+        "Halve the grid",
+        Command::ShrinkGrid,
+    ),
+    bind(
+        &[Key::Y],
+        "Y",
+        "Copy the live cells as RLE",
+        Command::CopyPattern,
+    ),
+    Binding {
+        shift: true,
+        ..bind(
+            &[Key::Y],
+            "Shift+Y",
+            "Paste an RLE or Life 1.06 pattern",
+            Command::PastePattern,
+        )
+    },
+    bind(&[Key::P], "P", "Save a screenshot", Command::Screenshot),
+    bind(
+        &[Key::I],
+        "I",
+        "Export the grid as a PNG",
+        Command::ExportGrid,
+    ),
+    bind(
+        &[Key::W],
+        "W",
+        "Save the grid as a snapshot",
+        Command::SaveGrid,
+    ),
+];
+
+/// The mouse controls, handled apart from the keys.
+pub const MOUSE: &[(&str, &str)] = &[
+    ("Left click", "Toggle a cell"),
+    ("Left drag", "Draw live cells"),
+    ("Right drag", "Erase cells"),
+    ("Ctrl+Left drag", "Draw cells that stay alive"),
+    ("Ctrl+Right drag", "Draw cells that stay dead"),
+    ("Middle drag", "Pan"),
+    ("Wheel", "Zoom"),
+];
+
+/// The command bound to `key`. A binding that needs Shift wins over one of the same key
+/// without it.
+pub fn command(key: Key, shift: bool) -> Option<Command> {
+    let bound = |needs_shift: bool| {
+        BINDINGS
+            .iter()
+            .find(|binding| binding.keys.contains(&key) && binding.shift == needs_shift)
+    };
+    shift
+        .then(|| bound(true))
+        .flatten()
+        .or_else(|| bound(false))
+        .map(|binding| binding.command)
+}
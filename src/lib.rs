@@ -0,0 +1,49 @@
+//! The Game of Life on the GPU, apart from any window.
+//!
+//! A [`Simulation`](simulation::Simulation) owns the cell buffers and the compute pipeline
+//! that advances them, a [`Renderer`](renderer::Renderer) the pipeline that draws them into
+//! a texture view. Both only need a `wgpu` device, so they run the same in a window, in a
+//! test or headless:
+//!
+//! - [`Simulation::new`](simulation::Simulation::new) creates the grid from [`Settings`](simulation::Settings) and a rule
+//! - [`Simulation::advance`](simulation::Simulation::advance) steps the given number of generations
+//! - [`Simulation::load_cells`](simulation::Simulation::load_cells) sets the state of every cell
+//! - [`Simulation::read_cells`](simulation::Simulation::read_cells) reads the state of every cell back
+//! - [`Simulation::on_generation`](simulation::Simulation::on_generation) calls a closure after each generation
+//! - [`Renderer::render_to`](renderer::Renderer::render_to) draws the current generation into a view
+//!
+//! ```no_run
+//! # fn run(
+//! #     device: std::sync::Arc<wgpu::Device>,
+//! #     queue: std::sync::Arc<wgpu::Queue>,
+//! #     view: &wgpu::TextureView,
+//! # ) -> anyhow::Result<()> {
+//! use your_first_wgpu_app::{
+//!     renderer::Renderer,
+//!     rule::Rule,
+//!     simulation::{Settings, Simulation},
+//! };
+//!
+//! let mut simulation = Simulation::new(device, queue, &Settings::default(), Rule::default(), None)?;
+//! let renderer = Renderer::new(&simulation, wgpu::TextureFormat::Rgba8UnormSrgb);
+//!
+//! simulation.advance(10);
+//! let mut encoder = simulation.device().create_command_encoder(&Default::default());
+//! renderer.render_to(&mut encoder, view, &simulation, wgpu::Color::BLACK);
+//! simulation.queue().submit([encoder.finish()]);
+//!
+//! let alive = simulation.read_cells().iter().filter(|&&cell| cell == 1).count();
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod golden;
+pub mod gpu;
+pub mod load_error;
+pub mod pattern;
+pub mod reference;
+pub mod render_target;
+pub mod renderer;
+pub mod rule;
+pub mod simulation;
+pub mod snapshot;
@@ -0,0 +1,248 @@
+//! Why a pattern, snapshot or image couldn't be loaded into the grid.
+
+use std::fmt;
+
+use crate::simulation::GridSize;
+
+/// An error loading a file with [`Pattern::parse`](crate::pattern::Pattern::parse),
+/// [`snapshot::read`](crate::snapshot::read) or [`snapshot::read_image`](crate::snapshot::read_image),
+/// or placing a pattern in the grid.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// The image couldn't be decoded.
+    Image(image::ImageError),
+    /// The file doesn't start with the magic of a `.life` snapshot.
+    BadMagic,
+    /// The snapshot was written by a different version of the format.
+    UnsupportedVersion(u32),
+    /// The snapshot is shorter than its header says, in bytes.
+    Truncated { expected: usize, found: usize },
+    /// The snapshot is longer than its header says, in bytes.
+    TrailingData { expected: usize, found: usize },
+    /// The grid in the file isn't the size of the grid it is loaded into.
+    DimensionMismatch { expected: GridSize, found: GridSize },
+    /// The rule in the pattern file isn't one the simulation can run.
+    UnsupportedRule(String),
+    /// The pattern file isn't valid RLE or Life 1.06, from the character at `line` and
+    /// `column`, both counted from 1.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The pattern doesn't fit in the grid.
+    PatternTooLarge { pattern: GridSize, grid: GridSize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{err}"),
+            LoadError::Image(err) => write!(f, "{err}"),
+            LoadError::BadMagic => write!(f, "not a .life snapshot"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            LoadError::Truncated { expected, found } => write!(
+                f,
+                "truncated after {found} of {expected} bytes"
+            ),
+            LoadError::TrailingData { expected, found } => write!(
+                f,
+                "expected {expected} bytes but found {found}, the file may be corrupt"
+            ),
+            LoadError::DimensionMismatch { expected, found } => write!(
+                f,
+                "holds a {found} grid, but the grid size is {expected}, pass '--grid-size {found}' to load it"
+            ),
+            LoadError::UnsupportedRule(err) => write!(f, "unsupported rule {err}"),
+            LoadError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+            LoadError::PatternTooLarge { pattern, grid } => write!(
+                f,
+                "the {pattern} pattern doesn't fit in a {grid} grid, pass a larger '--grid-size'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            LoadError::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for LoadError {
+    fn from(err: image::ImageError) -> Self {
+        LoadError::Image(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{pattern::Pattern, snapshot};
+
+    /// Write `data` to a file of the temporary directory, unique to this test run.
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("load-error-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).expect("Failed to write the test file");
+        path
+    }
+
+    /// A `.life` snapshot with the header given as words, and `cells` cells after it.
+    fn snapshot_file(name: &str, magic: &[u8; 4], header: [u32; 3], cells: usize) -> PathBuf {
+        let mut data = magic.to_vec();
+        for word in header {
+            data.extend(word.to_le_bytes());
+        }
+        data.resize(data.len() + cells * 4, 0);
+        temp_file(name, &data)
+    }
+
+    fn read_snapshot(path: PathBuf) -> LoadError {
+        let result = snapshot::read(&path);
+        std::fs::remove_file(path).ok();
+        result.expect_err("Read a malformed snapshot")
+    }
+
+    /// The line and column of the syntax error parsing `text`.
+    fn syntax_position(text: &str) -> (usize, usize) {
+        match Pattern::parse(text).err() {
+            Some(LoadError::Syntax { line, column, .. }) => (line, column),
+            err => panic!("Expected a syntax error, got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn io() {
+        let path = std::env::temp_dir().join("load-error-missing.life");
+        assert!(matches!(snapshot::read(&path), Err(LoadError::Io(_))));
+    }
+
+    #[test]
+    fn image() {
+        let path = temp_file("image.png", b"not a png");
+        let result = snapshot::read_image(&path, GridSize::square(8), 0.5);
+        std::fs::remove_file(path).ok();
+        assert!(matches!(result, Err(LoadError::Image(_))));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let err = read_snapshot(snapshot_file("magic.life", b"EFIL", [1, 2, 2], 4));
+        assert!(matches!(err, LoadError::BadMagic));
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let err = read_snapshot(snapshot_file("version.life", b"LIFE", [7, 2, 2], 4));
+        assert!(matches!(err, LoadError::UnsupportedVersion(7)));
+    }
+
+    #[test]
+    fn truncated() {
+        let err = read_snapshot(snapshot_file("truncated.life", b"LIFE", [1, 2, 2], 3));
+        assert!(matches!(
+            err,
+            LoadError::Truncated {
+                expected: 32,
+                found: 28
+            }
+        ));
+        let err = read_snapshot(temp_file("header.life", b"LIFE\x01\x00"));
+        assert!(matches!(
+            err,
+            LoadError::Truncated {
+                expected: 16,
+                found: 6
+            }
+        ));
+    }
+
+    #[test]
+    fn trailing_data() {
+        let err = read_snapshot(snapshot_file("trailing.life", b"LIFE", [1, 2, 2], 5));
+        assert!(matches!(
+            err,
+            LoadError::TrailingData {
+                expected: 32,
+                found: 36
+            }
+        ));
+    }
+
+    #[test]
+    fn dimension_mismatch() {
+        let err = LoadError::DimensionMismatch {
+            expected: GridSize::square(32),
+            found: GridSize::square(2),
+        };
+        assert!(err.to_string().contains("pass '--grid-size 2x2'"));
+    }
+
+    #[test]
+    fn unsupported_rule() {
+        let err = Pattern::parse("x = 1, y = 1, rule = B3S23\no!").err();
+        assert!(
+            matches!(err, Some(LoadError::UnsupportedRule(rule)) if rule.starts_with("'B3S23'"))
+        );
+    }
+
+    #[test]
+    fn rle_syntax() {
+        assert_eq!(syntax_position(""), (1, 1));
+        assert_eq!(syntax_position("#C only a comment\n"), (2, 1));
+        assert_eq!(syntax_position("#N header\n  x = 3\n3o!"), (2, 3));
+        assert_eq!(syntax_position("x = 3, y = 1\n3o?!"), (2, 3));
+        // The run of two cells after the first goes past the width of two.
+        assert_eq!(syntax_position("x = 2, y = 1\n  o2o!"), (2, 5));
+        assert_eq!(syntax_position("x = 1, y = 1\n$o!"), (2, 2));
+    }
+
+    #[test]
+    fn rle_run_too_long() {
+        // The run doesn't fit in a usize, and is rejected at the tag it repeats.
+        assert_eq!(
+            syntax_position("x = 3, y = 1\n99999999999999999999999o!"),
+            (2, 24)
+        );
+        assert_eq!(syntax_position("x = 3, y = 1\n4bo!"), (2, 2));
+        assert_eq!(syntax_position("x = 3, y = 2\no3$o!"), (2, 3));
+        // A run of dead cells to the edge, or a `$` ending the last row, is fine.
+        assert!(Pattern::parse("x = 3, y = 2\n2bo$3b$!").is_ok());
+    }
+
+    #[test]
+    fn life_106_syntax() {
+        assert_eq!(syntax_position("#Life 1.06\n0 0\n1 0\n\n  1 x\n"), (5, 3));
+    }
+
+    #[test]
+    fn pattern_too_large() {
+        let glider = Pattern::preset("glider").expect("Unknown built-in pattern");
+        let err = glider.place(GridSize::square(2)).err();
+        assert!(matches!(
+            err,
+            Some(LoadError::PatternTooLarge { pattern, grid })
+                if pattern == GridSize::square(3) && grid == GridSize::square(2)
+        ));
+    }
+}
@@ -1,474 +1,1435 @@
-use std::time::{Duration, Instant};
+mod args;
+mod camera;
+mod config;
+mod cycles;
+mod generation_label;
+mod history;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod keys;
+mod minimap;
+mod pixelated;
+mod recorder;
+mod state;
+mod timing;
+mod timing_csv;
+mod ui;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
-use rand::prelude::Distribution;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use instant::Instant;
 use winit::{
-    event::{Event, StartCause, WindowEvent},
+    dpi::PhysicalPosition,
+    event::{
+        ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
+use your_first_wgpu_app::{gpu, render_target, renderer, rule, simulation, snapshot};
 
-const GRID_SIZE: usize = 32;
-const WORKGROUP_SIZE: usize = 8;
+use args::Args;
+use keys::Command;
+use recorder::Recorder;
+use simulation::{Boundary, SimBackend, Simulation};
+use state::State;
+use timing::FrameClock;
+use timing_csv::TimingCsv;
+use ui::Controls;
+// Only used by the headless modes, loading the grid from the command line and the
+// clipboard, none of which the browser has.
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    cycles::CycleDetector,
+    golden::GOLDENS,
+    load_error::LoadError,
+    pattern::Pattern,
+    rule::Rule,
+    simulation::{GridSize, Settings},
+    std::sync::Arc,
+    timing::LateSteps,
+    your_first_wgpu_app::{golden, load_error, pattern, reference},
+};
 
-fn byte_length<T>(vec: &Vec<T>) -> u64 {
-    (vec.len() * std::mem::size_of::<T>()) as u64
-}
+const TITLE: &str = "Your first wgpu app";
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_HEADLESS_GENERATIONS: u64 = 100;
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_TRIALS: u64 = 100;
 
-async fn run(event_loop: EventLoop<()>, window: Window) {
-    let instance = wgpu::Instance::default();
-
-    // Surface is unique to the Rust API of wgpu. In the WebGPU specification, GPUCanvasContext serves a similar role.
-    // Source: https://docs.rs/wgpu/latest/wgpu/struct.Surface.html
-    //
-    // ```js
-    // const context = canvas.getContext("webgpu");
-    // ```
-    let surface = unsafe { instance.create_surface(&window) }.expect("");
-
-    // ```js
-    // const adapter = await navigator.gpu.requestAdapter();
-    // if (!adapter) {
-    //     throw new Error("No appropriate GPUAdapter found.");
-    // }
-    // ```
-    let options = wgpu::RequestAdapterOptions {
-        compatible_surface: Some(&surface),
-        ..Default::default()
+fn update_title(state: &State, fps: f64, paused: bool) {
+    let paused = if paused { " (paused)" } else { "" };
+    let fps = if state.is_rendering() {
+        format!("{fps:.0} fps")
+    } else {
+        "rendering off".to_string()
     };
-    let adapter = instance
-        .request_adapter(&options)
-        .await
-        .expect("No appropriate adapter found");
-
-    // ```js
-    // const device = await adapter.requestDevice();
-    // ```
-    let desc = wgpu::DeviceDescriptor::default();
-    let (device, queue) = adapter
-        .request_device(&desc, None)
-        .await
-        .expect("Device request failed");
-
-    // ```js
-    // const canvasFormat = navigator.gpu.getPreferredCanvasFormat();
-    // context.configure({
-    //     device: device,
-    //     format: canvasFormat,
-    // });
-    // ```
-    let size = window.inner_size();
-    let config = surface
-        .get_default_config(&adapter, size.width, size.height)
-        .expect("No default surface config");
-    surface.configure(&device, &config);
-
-    let uniform_array = vec![GRID_SIZE as f32, GRID_SIZE as f32];
-    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: Some("Grid Uniforms"),
-        size: byte_length(&uniform_array),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
-    });
+    let rule = state.simulation().rule();
+    let rule = match rule.name() {
+        Some(name) => format!("{name} ({rule})"),
+        None => rule.to_string(),
+    };
+    let generation = state.generation();
+    state.window().set_title(&format!(
+        "{TITLE} — {rule} — gen {generation} — {fps}{paused}"
+    ));
+}
 
-    queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&uniform_array));
-
-    #[rustfmt::skip]
-    let vertices: Vec<f32> = vec![
-        // X,   Y
-        -0.8, -0.8, // Triangle 1
-         0.8, -0.8,
-         0.8,  0.8,
-        -0.8, -0.8, // Triangle 2
-         0.8,  0.8,
-        -0.8,  0.8,
-    ];
+async fn run(
+    event_loop: EventLoop<()>,
+    window: Window,
+    args: Args,
+    initial_cells: Option<Vec<u32>>,
+) -> anyhow::Result<()> {
+    // Until the first frame is presented, to log how long startup took.
+    let mut startup = Some(Instant::now());
+    let mut state = State::new(window, &args, initial_cells).await?;
+    log_summary(
+        &args,
+        state.adapter_info(),
+        &state.simulation().device().limits(),
+        Some(state.present_mode()),
+    );
 
-    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: Some("Cell vertices"),
-        size: byte_length(&vertices),
-        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
-    });
+    let mut controls = Controls::new(&args);
+    let mut held_keys = HashSet::new();
+    let mut cursor_position = PhysicalPosition::new(0.0, 0.0);
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut clipboard = None;
+    let mut panning = false;
+    // Whether the left (paint) or right (erase) button is held, and whether Ctrl was held
+    // when it was pressed to fix the cells.
+    let mut drawing: Option<(bool, bool)> = None;
+    // The cell the left button was pressed on, until the cursor leaves it. Releasing the
+    // button before then is a click, which toggles the cell instead of drawing.
+    let mut clicked_cell = None;
+    // Paused while another window has focus, separately from a pause by the user.
+    let mut blurred = false;
 
-    queue.write_buffer(&vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+    // Steps are submitted from this thread, between frames. Submitting them from another
+    // thread wouldn't keep a slow step from delaying the next frame: the compute and render
+    // passes share the one queue, so the frame is only drawn once the step before it is
+    // done. The renderer would also have to take the simulation's lock around choosing
+    // the bind group of the newest generation and submitting the frame, so it never draws
+    // from a buffer that a submitted step is still writing. Slow steps are limited by
+    // `MAX_STEPS_PER_FRAME` and `--steps-per-submit` instead. The longest tick still
+    // fits in a frame.
+    const MAX_STEPS_PER_FRAME: u32 = ui::MAX_STEPS_PER_TICK;
+    let mut clock = FrameClock::new(args.max_fps, args.late_steps);
+    // Generations advanced since the last frame, including single steps.
+    let mut steps = 0;
+    // Generations advanced in total, to exit after `--generations`.
+    let mut total_steps = 0;
+    let step_limit = args.generations.unwrap_or(u64::MAX);
 
-    let vertex_buffer_layout = wgpu::VertexBufferLayout {
-        array_stride: 8,
-        step_mode: wgpu::VertexStepMode::Vertex, // WebGPU defaults to `GPUVertexStepMode stepMode = "vertex";`
-        attributes: &[wgpu::VertexAttribute {
-            format: wgpu::VertexFormat::Float32x2,
-            offset: 0,
-            shader_location: 0,
-        }],
-    };
+    let mut recorder = args
+        .record
+        .as_ref()
+        .map(|path| {
+            let size = state.window().inner_size();
+            log::info!("Recording {} frames to {}", args.frames, path.display());
+            Recorder::new(path, size.width, size.height, args.frames)
+                .with_context(|| format!("Failed to create {}", path.display()))
+        })
+        .transpose()?;
 
-    // Create an array representing the active state of each cell.
-    let mut cell_state_array = vec![0u32; GRID_SIZE * GRID_SIZE];
-
-    // Create two storage buffers to hold the cell state.
-    let cell_state_storage = [
-        device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Cell State A"),
-            size: byte_length(&cell_state_array),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
-        }),
-        device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Cell State B"),
-            size: byte_length(&cell_state_array),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
-        }),
-    ];
+    let mut timing_csv = args
+        .timing_csv
+        .as_ref()
+        .map(|path| {
+            log::info!("Writing frame timings to {}", path.display());
+            TimingCsv::new(path).with_context(|| format!("Failed to create {}", path.display()))
+        })
+        .transpose()?;
+    let mut last_frame = Instant::now();
 
-    // Set each cell to a random state, then copy the array into the storage buffer.
-    let mut rng = rand::thread_rng();
-    let dist = rand::distributions::Bernoulli::new(0.6).unwrap();
-    for cell in cell_state_array.iter_mut() {
-        *cell = dist.sample(&mut rng) as u32;
-    }
-    queue.write_buffer(
-        &cell_state_storage[0],
-        0,
-        bytemuck::cast_slice(&cell_state_array),
-    );
+    // Grid snapshot waiting for its readback to finish.
+    let mut pending_save: Option<(PathBuf, gpu::PendingRead)> = None;
 
-    let cell_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Cell shader"),
-        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(
-            "
-            struct VertexInput {
-                @location(0) pos: vec2f,
-                @builtin(instance_index) instance: u32,
-            };
-
-            struct VertexOutput {
-                @builtin(position) pos: vec4f,
-                @location(0) cell: vec2f,
-            };
-
-            @group(0) @binding(0) var<uniform> grid: vec2f;
-            @group(0) @binding(1) var<storage> cell_state: array<u32>; 
-
-            @vertex
-            fn vertexMain(input: VertexInput) -> VertexOutput {
-
-                let i = f32(input.instance);
-                let cell = vec2f(i % grid.x, floor(i / grid.x));
-                let state = f32(cell_state[input.instance]);
-
-                let cell_offset = cell / grid * 2.0;
-                let grid_pos = (input.pos * state + 1.0) / grid - 1.0 + cell_offset;
-
-                var output: VertexOutput;
-                output.pos = vec4f(grid_pos, 0.0, 1.0);
-                output.cell = cell;
-                return output;
-            }
+    // Frames rendered since the FPS was last computed.
+    let mut frames = 0;
+    let mut fps_start = Instant::now();
+    let mut fps = 0.0;
 
-            @fragment
-            fn fragmentMain(input: VertexOutput) -> @location(0) vec4f {
-                let c = input.cell / grid;
-                return vec4f(c, 1.0-c.x, 1.0);
-            }
-        ",
-        )),
-    });
+    #[cfg(feature = "hot-reload")]
+    let shader_watcher = args
+        .watch
+        .then(|| {
+            let dir = std::path::Path::new(hot_reload::SHADER_DIR);
+            log::info!("Watching {} for shader changes", dir.display());
+            hot_reload::ShaderWatcher::new(dir).context("Failed to watch the shader directory")
+        })
+        .transpose()?;
 
-    // Create the compute shader that will process the simulation.
-    let simulation_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Game of Life simulation shader"),
-        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(
-            "
-            @group(0) @binding(0) var<uniform> grid: vec2f;
-            @group(0) @binding(1) var<storage> cell_state_in: array<u32>;
-            @group(0) @binding(2) var<storage, read_write> cell_state_out: array<u32>;
-
-            fn cell_index(cell: vec2<i32>) -> u32 {
-                return u32(
-                    ((cell.y + i32(grid.y)) % i32(grid.y)) * i32(grid.x) +
-                    ((cell.x + i32(grid.x)) % i32(grid.x))
-                );
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            // The control panel gets the first look at window events, so clicking a
+            // slider doesn't also toggle the cell below it.
+            Event::WindowEvent { ref event, .. } if state.ui_event(event) => {
+                state.window().request_redraw();
             }
+            Event::MainEventsCleared => {
+                // Keep drawing while running, but only redraw on input while paused.
+                // Still wake up now and then to finish saves and reload shaders.
+                const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+                // Nothing is drawn while minimized, which pauses the simulation too.
+                let paused = controls.paused || blurred || state.is_minimized();
+                let now = Instant::now();
+                let next_step =
+                    (!state.is_rendering()).then(|| clock.next_step(controls.update_interval));
+                *control_flow = timing::control_flow(
+                    now,
+                    clock.next_frame(now),
+                    next_step,
+                    paused.then_some(PAUSED_POLL_INTERVAL),
+                );
+                if *control_flow == ControlFlow::Poll {
+                    state.window().request_redraw();
+                }
+
+                #[cfg(feature = "hot-reload")]
+                if let Some(watcher) = &shader_watcher {
+                    for path in watcher.changed() {
+                        state.reload_shader(&path);
+                    }
+                }
 
-            fn cell_active(x: i32, y: i32) -> u32 {
-                return cell_state_in[cell_index(vec2(x, y))];
+                if state.update_hover() {
+                    state.window().request_redraw();
+                }
+
+                if let Some((path, pending)) = &pending_save {
+                    let simulation = state.simulation();
+                    if let Some(data) = pending.try_read(simulation.device()) {
+                        let size = simulation.grid_size();
+                        let (width, height) = (size.width as u32, size.height as u32);
+                        match snapshot::write(path, width, height, bytemuck::cast_slice(&data)) {
+                            Ok(()) => log::info!("Saved {}", path.display()),
+                            Err(err) => log::error!("Failed to save {}: {err}", path.display()),
+                        }
+                        pending_save = None;
+                    }
+                }
             }
+            Event::RedrawRequested(_) => {
+                clock.start_frame(Instant::now(), !controls.paused && !blurred);
 
-            @compute
-            @workgroup_size(${WORKGROUP_SIZE},${WORKGROUP_SIZE})
-            fn computeMain(@builtin(global_invocation_id) cell: vec3u) {
+                // Record the generations that are due into as few submissions as allowed.
+                // Each tick of the update interval runs `steps_per_tick` generations, a
+                // tick is never split over submissions or frames.
+                let per_tick = controls.steps_per_tick;
+                while total_steps < step_limit {
+                    let limit = (args.steps_per_submit / per_tick)
+                        .max(1)
+                        .min(MAX_STEPS_PER_FRAME.saturating_sub(steps) / per_tick);
+                    let ticks = clock.take_steps(controls.update_interval, limit);
+                    if ticks == 0 {
+                        break;
+                    }
+                    let remaining = (step_limit - total_steps).min(u32::MAX as u64) as u32;
+                    let batch = (ticks * per_tick).min(remaining);
+                    state.advance(batch);
+                    steps += batch;
+                    total_steps += batch as u64;
+                }
+                if args.auto_reset && state.is_settled() {
+                    log::info!("Re-seeding the settled grid");
+                    state.reseed(controls.density);
+                }
 
-                let cell = vec2i(cell.xy); 
+                // With rendering off the generations still advance, but nothing is drawn.
+                if state.is_rendering() {
+                    state.set_progress(clock.progress(controls.update_interval));
+                    let paused = controls.paused;
+                    if let Err(err) = state.render(&mut controls) {
+                        log::error!("Failed to render: {err}");
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    if let Some(start) = startup.take() {
+                        log::info!("First frame after {:?}", start.elapsed());
+                    }
+
+                    // Apply what was changed in the control panel.
+                    if controls.paused != paused {
+                        clock.reset();
+                        update_title(&state, fps, controls.paused);
+                    }
+                    if std::mem::take(&mut controls.reseed) {
+                        state.reseed(controls.density);
+                        state.window().request_redraw();
+                    }
+                    state.set_alive_color(controls.alive_color);
 
-                // Determine how many active neighbors this cell has.
-                let active_neighbors = cell_active(cell.x + 1, cell.y + 1) +
-                                       cell_active(cell.x + 1, cell.y) +
-                                       cell_active(cell.x + 1, cell.y - 1) +
-                                       cell_active(cell.x,     cell.y - 1) +
-                                       cell_active(cell.x - 1, cell.y - 1) +
-                                       cell_active(cell.x - 1, cell.y) +
-                                       cell_active(cell.x - 1, cell.y + 1) +
-                                       cell_active(cell.x,     cell.y + 1);
+                    // Record every frame that shows a new generation.
+                    if let (true, Some(rec)) = (steps > 0, recorder.as_mut()) {
+                        let delay = controls.update_interval * steps.div_ceil(per_tick);
+                        let recorded = state.capture_frame().and_then(|(width, height, pixels)| {
+                            rec.add_frame(width, height, &pixels, delay)
+                                .map_err(|err| err.to_string())
+                        });
+                        match recorded {
+                            Ok(false) => {}
+                            Ok(true) => {
+                                let rec = recorder.take().expect("The recorder was just used");
+                                *control_flow = finish_recording(rec, &args);
+                            }
+                            // A partial recording isn't what was asked for, stop with an error.
+                            Err(err) => {
+                                log::error!(
+                                    "Failed to record frame {} to {}: {err}",
+                                    state.generation(),
+                                    args.record.as_ref().expect("Recording").display()
+                                );
+                                recorder = None;
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                            }
+                        }
+                    }
+
+                    if let Some(csv) = &mut timing_csv {
+                        let now = Instant::now();
+                        let gpu_time = state.take_gpu_time();
+                        if let Err(err) =
+                            csv.add_frame(state.generation(), now - last_frame, gpu_time)
+                        {
+                            log::error!("Failed to write frame timings: {err}");
+                            timing_csv = None;
+                        }
+                        last_frame = now;
+                    }
+                    frames += 1;
+                }
+                steps = 0;
 
-                let i = cell_index(cell);
+                // The last generation has been advanced to, and presented unless rendering is off.
+                // Once exiting, the exit code of a failed recording is kept.
+                let exiting = matches!(*control_flow, ControlFlow::ExitWithCode(_));
+                if total_steps >= step_limit && !exiting {
+                    log::info!("Ran {total_steps} generations, exiting");
+                    *control_flow = match recorder.take() {
+                        Some(rec) => finish_recording(rec, &args),
+                        None => ControlFlow::Exit,
+                    };
+                }
 
-                // Conway's game of life rules:
-                switch active_neighbors {
-                    case 2u: { // Active cells with 2 neighbors stay active.
-                        cell_state_out[i] = cell_state_in[i];
+                // Only format a new title once a second.
+                let elapsed = fps_start.elapsed();
+                if elapsed >= Duration::from_secs(1) {
+                    fps = frames as f64 / elapsed.as_secs_f64();
+                    frames = 0;
+                    fps_start = Instant::now();
+                    update_title(&state, fps, controls.paused);
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            // The event loop doesn't return, so the rows still buffered are written here.
+            Event::LoopDestroyed => {
+                if let Some(csv) = &mut timing_csv {
+                    if let Err(err) = csv.flush() {
+                        log::error!("Failed to write frame timings: {err}");
                     }
-                    case 3u: { // Cells with 3 neighbors become or stay active.
-                        cell_state_out[i] = 1u;
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                ..
+            } if args.pause_on_blur => {
+                log::debug!("Window {}", if focused { "focused" } else { "blurred" });
+                blurred = !focused;
+                // Don't catch up on the time spent in the background.
+                clock.reset();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                state.resize(size);
+                // Don't catch up on the time spent minimized.
+                clock.reset();
+            }
+            // Moving to a monitor of another scale keeps the logical size of the window, the
+            // surface follows its new physical size. The panel was rescaled by `ui_event`.
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { new_inner_size, .. },
+                ..
+            } => state.resize(*new_inner_size),
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: key_state,
+                                virtual_keycode: Some(key),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // Only react to the initial press, not to the repeats generated while a key is held.
+                let pressed = match key_state {
+                    ElementState::Pressed => held_keys.insert(key),
+                    ElementState::Released => {
+                        held_keys.remove(&key);
+                        false
                     }
-                    default: { // Cells with < 2 or > 3 neighbors become inactive.
-                        cell_state_out[i] = 0u;
+                };
+
+                let shift = held_keys.contains(&VirtualKeyCode::LShift)
+                    || held_keys.contains(&VirtualKeyCode::RShift);
+                if pressed {
+                    match keys::command(key, shift) {
+                        Some(command) if cfg!(target_arch = "wasm32") && command.reads_back() => {
+                            log::warn!("{command:?} isn't available in the browser");
+                        }
+                        Some(Command::Pause) => {
+                            controls.paused = !controls.paused;
+                            clock.reset();
+                            update_title(&state, fps, controls.paused);
+                        }
+                        // Single step one generation while paused.
+                        Some(Command::Step) if controls.paused && total_steps < step_limit => {
+                            state.update();
+                            steps += 1;
+                            total_steps += 1;
+                        }
+                        // Move through the history while paused, computing new
+                        // generations when stepping forward past its end.
+                        Some(Command::StepBack) if controls.paused => {
+                            state.step_back();
+                        }
+                        Some(Command::StepForward) if controls.paused => {
+                            let redone = state.step_forward();
+                            if !redone && total_steps < step_limit {
+                                state.update();
+                                steps += 1;
+                                total_steps += 1;
+                            }
+                        }
+                        // Speed up or slow down the simulation.
+                        Some(Command::SpeedUp) => controls.speed_up(),
+                        Some(Command::SlowDown) => controls.slow_down(),
+                        // Run more or fewer generations per tick.
+                        Some(Command::MoreStepsPerTick) => controls.more_steps_per_tick(),
+                        Some(Command::FewerStepsPerTick) => controls.fewer_steps_per_tick(),
+                        Some(Command::Screenshot) => {
+                            let path = format!("screenshot-{}.png", timestamp());
+                            match state.screenshot(Path::new(&path)) {
+                                Ok(()) => log::info!("Saved {path}"),
+                                Err(err) => log::error!("Failed to save {path}: {err}"),
+                            }
+                        }
+                        Some(Command::ExportGrid) => {
+                            let simulation = state.simulation();
+                            export_grid(simulation, &simulation.read_cells());
+                        }
+                        // Save the grid once it has been read back, see below.
+                        Some(Command::SaveGrid) if pending_save.is_none() => {
+                            let path = PathBuf::from(format!("grid-{}.life", timestamp()));
+                            pending_save = Some((path, state.simulation().read_cells_async()));
+                        }
+                        // Re-seed the grid with a fresh random state.
+                        Some(Command::Reseed) => state.reseed(controls.density),
+                        // Start from a blank or full grid.
+                        Some(Command::Clear) => state.fill(false),
+                        Some(Command::Fill) => state.fill(true),
+                        // Brush sizes from 1 to 9 cells.
+                        Some(Command::BrushSize) => {
+                            state.set_brush_size(key as usize - VirtualKeyCode::Key1 as usize + 1);
+                        }
+                        // Cycle the colors of live cells.
+                        Some(Command::CyclePalette) => state.cycle_palette(),
+                        Some(Command::CycleGradient) => state.cycle_gradient(),
+                        Some(Command::CycleRule) => {
+                            state.cycle_rule();
+                            update_title(&state, fps, controls.paused);
+                        }
+                        Some(Command::PopulationGraph) => state.toggle_population_graph(),
+                        Some(Command::Invert) => state.toggle_invert(),
+                        Some(Command::HighContrast) => state.toggle_high_contrast(),
+                        Some(Command::Minimap) => state.toggle_minimap(),
+                        Some(Command::GenerationLabel) => state.toggle_generation_label(),
+                        Some(Command::Rendering) => {
+                            state.toggle_rendering();
+                            update_title(&state, fps, controls.paused);
+                        }
+                        Some(Command::Heatmap) => state.toggle_heatmap(),
+                        Some(Command::Fullscreen) => state.toggle_fullscreen(),
+                        Some(Command::Help) => state.toggle_help(),
+                        // Copy the grid to the clipboard as RLE, or paste a pattern.
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some(Command::CopyPattern) => {
+                            if let Err(err) = copy_pattern(&state, &mut clipboard) {
+                                log::error!("{err}");
+                            }
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some(Command::PastePattern) => {
+                            if let Err(err) =
+                                paste_pattern(&mut state, &mut clipboard, cursor_position)
+                            {
+                                log::error!("{err}");
+                            }
+                        }
+                        // Double or halve the grid, keeping the top-left corner. R re-seeds it.
+                        Some(Command::GrowGrid) => {
+                            state.resize_grid(state.simulation().grid_size().scaled(2.0))
+                        }
+                        Some(Command::ShrinkGrid) => {
+                            state.resize_grid(state.simulation().grid_size().scaled(0.5))
+                        }
+                        _ => {}
                     }
+                    state.window().request_redraw();
                 }
             }
-        "
-            .to_string()
-            .replace("${WORKGROUP_SIZE}", &format!("{WORKGROUP_SIZE}")),
-        )),
-    });
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                // Drag the grid along while the middle button is held.
+                if panning {
+                    state.pan(cursor_position, position);
+                    state.window().request_redraw();
+                }
+                if let Some((alive, fixed)) = drawing {
+                    if clicked_cell.is_some() && state.cell_at(position) != clicked_cell {
+                        // A drag after all, which draws from where it started.
+                        clicked_cell = None;
+                        state.paint(cursor_position, alive, fixed);
+                    }
+                    if clicked_cell.is_none() {
+                        state.paint(position, alive, fixed);
+                        state.window().request_redraw();
+                    }
+                }
+                if state.hover(position) {
+                    state.window().request_redraw();
+                }
+                cursor_position = position;
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: button_state,
+                        button: MouseButton::Middle,
+                        ..
+                    },
+                ..
+            } => panning = button_state == ElementState::Pressed,
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                const PIXELS_PER_LINE: f64 = 40.0;
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+                };
+                state.zoom(cursor_position, notches);
+                state.window().request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: button_state,
+                        button: button @ (MouseButton::Left | MouseButton::Right),
+                        ..
+                    },
+                ..
+            } => {
+                // Draw with the left button and erase with the right one, holding Ctrl to
+                // keep the cells that way whatever the rule. A left click toggles the cell.
+                let fixed = held_keys.contains(&VirtualKeyCode::LControl)
+                    || held_keys.contains(&VirtualKeyCode::RControl);
+                let pressed = button_state == ElementState::Pressed;
+                let left = button == MouseButton::Left;
+                // Released on the cell it was pressed on, which only the left button sets.
+                if let (Some(_), Some((_, fixed))) = (clicked_cell.take(), drawing) {
+                    if !pressed {
+                        state.toggle(cursor_position, fixed);
+                        state.window().request_redraw();
+                    }
+                }
+                drawing = pressed.then_some((left, fixed));
+                if pressed && left {
+                    // Nothing is drawn until the cursor leaves the cell.
+                    clicked_cell = state.cell_at(cursor_position);
+                } else if let Some((alive, fixed)) = drawing {
+                    state.paint(cursor_position, alive, fixed);
 
-    // Create the bind group layout and pipeline layout.
-    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Cell Bind Group Layout"),
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX
-                    | wgpu::ShaderStages::COMPUTE
-                    | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-        ],
+                    // Redraw right away to show the painted cells.
+                    state.window().request_redraw();
+                }
+            }
+            _ => {}
+        }
     });
+}
 
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("Cell Pipeline Layout"),
-        bind_group_layouts: &[&bind_group_layout],
-        push_constant_ranges: &[],
-    });
+/// Close the GIF given with `--record`, and exit with an error if that failed.
+fn finish_recording(recorder: Recorder, args: &Args) -> ControlFlow {
+    let path = args.record.as_ref().expect("Recording");
+    match recorder.finish() {
+        Ok(()) => {
+            log::info!("Recording finished");
+            ControlFlow::Exit
+        }
+        Err(err) => {
+            log::error!("Failed to finish recording {}: {err}", path.display());
+            ControlFlow::ExitWithCode(1)
+        }
+    }
+}
 
-    let swapchain_capabilities = surface.get_capabilities(&adapter);
-    let swapchain_format = swapchain_capabilities.formats[0];
-
-    let cell_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Cell pipeline"),
-        vertex: wgpu::VertexState {
-            module: &cell_shader_module,
-            entry_point: "vertexMain",
-            buffers: &[vertex_buffer_layout],
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &cell_shader_module,
-            entry_point: "fragmentMain",
-            targets: &[Some(swapchain_format.into())],
-        }),
-        layout: Some(&pipeline_layout),
-        primitive: wgpu::PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    });
+/// Milliseconds since the Unix epoch, used to give saved files unique names.
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Read a pattern file, or a built-in pattern of that name.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_pattern(path: &Path) -> Result<Pattern, String> {
+    // Files take precedence over the built-in patterns of the same name.
+    let preset = path
+        .to_str()
+        .filter(|_| !path.exists())
+        .and_then(Pattern::preset);
+    match preset {
+        Some(pattern) => Ok(pattern),
+        None => std::fs::read_to_string(path)
+            .map_err(LoadError::Io)
+            .and_then(|text| Pattern::parse(&text))
+            .map_err(|err| {
+                format!(
+                    "Failed to load {}: {err}, see '--list-patterns' for the built-in patterns",
+                    path.display()
+                )
+            }),
+    }
+}
 
-    let bind_group = [
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Cell renderer bind group A"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        uniform_buffer.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        cell_state_storage[0].as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        cell_state_storage[1].as_entire_buffer_binding(),
-                    ),
-                },
-            ],
-        }),
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Cell renderer bind group B"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        uniform_buffer.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        cell_state_storage[1].as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        cell_state_storage[0].as_entire_buffer_binding(),
-                    ),
-                },
-            ],
-        }),
+/// Place `pattern` loaded from `path` at `offset` in a dead grid, warning about the cells
+/// that are off the grid.
+#[cfg(not(target_arch = "wasm32"))]
+fn place_pattern(pattern: &Pattern, path: &Path, offset: [isize; 2], args: &Args) -> Vec<u32> {
+    let (cells, outside) = pattern.place_at(args.grid_size, offset, args.boundary);
+    if outside > 0 {
+        let effect = match args.boundary {
+            Boundary::Wrap => "wrap around",
+            Boundary::Dead | Boundary::Border => "are left out",
+        };
+        log::warn!(
+            "{outside} cells of {} are {} at offset {},{} and {effect}",
+            path.display(),
+            if args.boundary == Boundary::Border {
+                "off the grid or on its border"
+            } else {
+                "off the grid"
+            },
+            offset[0],
+            offset[1]
+        );
+    }
+    cells
+}
+
+/// Place `pattern`, called `name` in the log, in the center of a dead grid. One larger
+/// than the grid is clipped to it with a warning.
+#[cfg(not(target_arch = "wasm32"))]
+fn place_centered(pattern: &Pattern, name: &str, grid_size: GridSize) -> Vec<u32> {
+    let err = match pattern.place(grid_size) {
+        Ok(cells) => return cells,
+        Err(err) => err,
+    };
+    let offset = [
+        (grid_size.width as isize - pattern.width as isize) / 2,
+        (grid_size.height as isize - pattern.height as isize) / 2,
     ];
+    let (cells, clipped) = pattern.place_at(grid_size, offset, Boundary::Dead);
+    log::warn!(
+        "Clipped {name} to the grid, leaving out {clipped} of its {} cells: {err}",
+        pattern.cells.len()
+    );
+    cells
+}
 
-    // Create a compute pipeline that updates the game state.
-    let simulation_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-        label: Some("Simulation pipeline"),
-        layout: Some(&pipeline_layout),
-        module: &simulation_shader_module,
-        entry_point: "computeMain",
-    });
+/// Stamp each of the `--pattern NAME@X,Y` patterns into one grid, warning about live cells
+/// that land on those of an earlier pattern. The rule comes from the first pattern that
+/// has one.
+#[cfg(not(target_arch = "wasm32"))]
+fn place_patterns(args: &mut Args) -> Result<Vec<u32>, String> {
+    let mut cells = vec![0; args.grid_size.cells()];
+    let mut rule = None;
+    for (path, offset) in &args.placed_patterns {
+        let pattern = load_pattern(path)?;
+        let placed = place_pattern(&pattern, path, *offset, args);
+        let mut collisions = 0;
+        for (cell, placed) in cells.iter_mut().zip(placed) {
+            collisions += (*cell & placed) as usize;
+            *cell |= placed;
+        }
+        if collisions > 0 {
+            log::warn!(
+                "{collisions} live cells of {} overlap earlier patterns",
+                path.display()
+            );
+        }
+        match (&rule, pattern.rule) {
+            (None, Some(pattern_rule)) => rule = Some((pattern_rule, path.clone())),
+            (Some((first, first_path)), Some(pattern_rule)) if pattern_rule != *first => {
+                log::warn!(
+                    "{} is for {pattern_rule}, using {first} from {}",
+                    path.display(),
+                    first_path.display()
+                );
+            }
+            _ => {}
+        }
+    }
+    if let Some((rule, path)) = rule {
+        log::info!("Using rule {rule} from {}", path.display());
+        args.rule = rule;
+    }
+    log::info!(
+        "Placed {} patterns, ignoring --density and --seed",
+        args.placed_patterns.len()
+    );
+    Ok(cells)
+}
 
-    const UPDATE_INTERVAL: Duration = Duration::new(0, 200_000_000);
-    let mut step = 0;
+/// Load the snapshot given with `--load`, the pattern given with `--pattern` or the
+/// image given with `--image`. A snapshot has to be the size of the grid, a larger
+/// pattern is clipped to it. A rule in the pattern file replaces `--rule`.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_initial_cells(args: &mut Args) -> Result<Option<Vec<u32>>, String> {
+    if !args.placed_patterns.is_empty() {
+        return place_patterns(args).map(Some);
+    }
 
-    event_loop.run(move |event, _, control_flow| {
-        // Have the closure take ownership of the resources.
-        // `event_loop.run` never returns, therefore we must do this to ensure
-        // the resources are properly cleaned up.
-        let _ = (&instance, &adapter, &cell_shader_module);
+    if let Some(path) = &args.pattern {
+        let pattern = load_pattern(path)?;
+        let cells = match args.pattern_offset {
+            Some(offset) => place_pattern(&pattern, path, offset, args),
+            None => place_centered(&pattern, &path.display().to_string(), args.grid_size),
+        };
+        if let Some(rule) = pattern.rule {
+            log::info!("Using rule {rule} from {}", path.display());
+            args.rule = rule;
+        }
+        log::info!("Loaded {}, ignoring --density and --seed", path.display());
+        return Ok(Some(cells));
+    }
 
-        match event {
-            Event::NewEvents(StartCause::Init) => {
-                control_flow.set_wait_until(Instant::now() + UPDATE_INTERVAL);
+    if let Some(path) = &args.image {
+        let (width, height, cells) =
+            snapshot::read_image(path, args.grid_size, args.image_threshold)
+                .map_err(|err| format!("Failed to load {}: {err}", path.display()))?;
+        log::info!(
+            "Loaded the {width}x{height} image {}, ignoring --density and --seed",
+            path.display()
+        );
+        return Ok(Some(cells));
+    }
+
+    if let Some(side) = args.seed_square {
+        let square = Pattern {
+            width: side,
+            height: side,
+            cells: (0..side)
+                .flat_map(|y| (0..side).map(move |x| (x, y)))
+                .collect(),
+            rule: None,
+        };
+        log::info!("Starting from a {side}x{side} square, ignoring --density and --seed");
+        return square
+            .place(args.grid_size)
+            .map(Some)
+            .map_err(|err| err.to_string());
+    }
+
+    let Some(path) = &args.load else {
+        return Ok(None);
+    };
+
+    let (width, height, cells) =
+        snapshot::read(path).map_err(|err| format!("Failed to load {}: {err}", path.display()))?;
+    let found = GridSize {
+        width: width as usize,
+        height: height as usize,
+    };
+    if found != args.grid_size {
+        let err = LoadError::DimensionMismatch {
+            expected: args.grid_size,
+            found,
+        };
+        return Err(format!("Failed to load {}: {err}", path.display()));
+    }
+
+    log::info!("Loaded {}, ignoring --density and --seed", path.display());
+    Ok(Some(cells))
+}
+
+/// The system clipboard, opened on first use. It is kept open after that, as on X11 the
+/// copied text is gone once the clipboard that set it is closed.
+#[cfg(not(target_arch = "wasm32"))]
+fn open_clipboard(
+    clipboard: &mut Option<arboard::Clipboard>,
+) -> Result<&mut arboard::Clipboard, String> {
+    if clipboard.is_none() {
+        let opened = arboard::Clipboard::new()
+            .map_err(|err| format!("Failed to open the clipboard: {err}"))?;
+        *clipboard = Some(opened);
+    }
+    Ok(clipboard.as_mut().expect("The clipboard was just opened"))
+}
+
+/// Copy the live cells to the clipboard as RLE, for sharing with other Life programs.
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_pattern(state: &State, clipboard: &mut Option<arboard::Clipboard>) -> Result<(), String> {
+    let pattern = state.pattern();
+    if pattern.cells.is_empty() {
+        return Err("There are no live cells to copy".to_string());
+    }
+    open_clipboard(clipboard)?
+        .set_text(pattern.to_rle())
+        .map_err(|err| format!("Failed to copy the pattern: {err}"))?;
+    log::info!(
+        "Copied the {}x{} pattern as RLE",
+        pattern.width,
+        pattern.height
+    );
+    Ok(())
+}
+
+/// Stamp the RLE or Life 1.06 pattern on the clipboard into the grid around `position`.
+/// The rule of the pattern, if any, isn't applied.
+#[cfg(not(target_arch = "wasm32"))]
+fn paste_pattern(
+    state: &mut State,
+    clipboard: &mut Option<arboard::Clipboard>,
+    position: PhysicalPosition<f64>,
+) -> Result<(), String> {
+    let text = open_clipboard(clipboard)?
+        .get_text()
+        .map_err(|err| format!("Failed to paste a pattern: {err}"))?;
+    let pattern =
+        Pattern::parse(&text).map_err(|err| format!("Failed to paste a pattern: {err}"))?;
+    state.stamp(&pattern, position);
+    log::info!("Pasted the {}x{} pattern", pattern.width, pattern.height);
+    Ok(())
+}
+
+/// Write the grid to `grid-{generation}.png`, one pixel per cell.
+fn export_grid(simulation: &Simulation, cells: &[u32]) {
+    let path = format!("grid-{:06}.png", simulation.generation());
+    let size = simulation.grid_size();
+    match snapshot::write_png(
+        Path::new(&path),
+        size.width as u32,
+        size.height as u32,
+        cells,
+    ) {
+        Ok(()) => log::info!("Saved {path}"),
+        Err(err) => log::error!("Failed to save {path}: {err}"),
+    }
+}
+
+/// Run the simulation without a window and print the final number of live cells.
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_headless(args: Args, initial_cells: Option<Vec<u32>>) -> anyhow::Result<()> {
+    if args.check_timing {
+        return check_timing(args.late_steps);
+    }
+    let instance = gpu::create_instance(args.backend);
+    let device = gpu::request_device(&instance, args.backend, args.gpu, None).await;
+    let (adapter, device, queue) = match device {
+        // Machines without a GPU can't run the checks, which isn't a failure of the checks.
+        Err(err) if args.check_goldens => {
+            log::warn!("Skipping the checks: {err}");
+            return Ok(());
+        }
+        device => device?,
+    };
+    log_summary(&args, &adapter.get_info(), &device.limits(), None);
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    if args.check {
+        return check_setup(device, queue, &args, &adapter.get_info(), initial_cells).await;
+    }
+    if args.check_goldens {
+        return check_goldens(device, queue, &args);
+    }
+    if args.bench_matrix {
+        let generations = args.generations.unwrap_or(DEFAULT_HEADLESS_GENERATIONS);
+        return bench_matrix(device, queue, &args, generations);
+    }
+    let comparison = args
+        .compare
+        .map(|rule| {
+            Simulation::new(
+                device.clone(),
+                queue.clone(),
+                &args.settings(),
+                rule,
+                initial_cells.clone(),
+            )
+        })
+        .transpose()?;
+    let mut simulation =
+        Simulation::new(device, queue, &args.settings(), args.rule, initial_cells)?;
+
+    let generations = args.generations.unwrap_or(DEFAULT_HEADLESS_GENERATIONS);
+    if args.benchmark {
+        benchmark(&mut simulation, generations, args.steps_per_submit);
+        return Ok(());
+    }
+    if args.verify {
+        return verify(&mut simulation, &args, generations);
+    }
+    if args.soup_search {
+        return soup_search(&mut simulation, &args, generations);
+    }
+
+    if let (Some(rule), Some(comparison)) = (args.compare, comparison) {
+        return compare([(args.rule, simulation), (rule, comparison)], generations);
+    }
+
+    for _ in 0..generations {
+        simulation.update();
+    }
+
+    let cells = simulation.read_cells();
+    if args.export_grid {
+        export_grid(&simulation, &cells);
+    }
+    let population = cells.iter().filter(|&&cell| cell == 1).count();
+    println!("Population after {generations} generations: {population}");
+    Ok(())
+}
+
+/// Create the simulations and the renderer as a run would, checking the grid and workgroup
+/// size against the limits of the device and compiling every shader, and print the
+/// configuration if that worked. Nothing is computed or drawn.
+#[cfg(not(target_arch = "wasm32"))]
+async fn check_setup(
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    args: &Args,
+    adapter: &wgpu::AdapterInfo,
+    initial_cells: Option<Vec<u32>>,
+) -> anyhow::Result<()> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let rules = std::iter::once(args.rule).chain(args.compare);
+    for rule in rules {
+        let simulation = Simulation::new(
+            device.clone(),
+            queue.clone(),
+            &args.settings(),
+            rule,
+            initial_cells.clone(),
+        )
+        .with_context(|| format!("Failed to set up {rule}"))?;
+        renderer::Renderer::new(&simulation, wgpu::TextureFormat::Rgba8UnormSrgb);
+    }
+    if let Some(err) = device.pop_error_scope().await {
+        anyhow::bail!("Failed to compile the shaders: {err}");
+    }
+    println!("OK");
+    println!("{}", summary(args, adapter, &device.limits(), None));
+    Ok(())
+}
+
+/// Run both rules from the same start and print their populations.
+#[cfg(not(target_arch = "wasm32"))]
+fn compare(simulations: [(Rule, Simulation); 2], generations: u64) -> anyhow::Result<()> {
+    for (rule, mut simulation) in simulations {
+        for _ in 0..generations {
+            simulation.update();
+        }
+        println!(
+            "Population after {generations} generations of {rule}: {}",
+            simulation.population()
+        );
+    }
+    Ok(())
+}
+
+/// Run a random soup from each of consecutive seeds for `generations` and print the seeds
+/// whose soups haven't died out or settled into still lifes and short oscillators by then.
+#[cfg(not(target_arch = "wasm32"))]
+fn soup_search(simulation: &mut Simulation, args: &Args, generations: u64) -> anyhow::Result<()> {
+    let period = cycles::MAX_PERIOD as u64;
+    if generations < period {
+        anyhow::bail!("'--soup-search' needs at least {period} generations");
+    }
+    let first_seed = args.seed.expect("The seed is chosen at startup");
+    let trials = args.trials.unwrap_or(DEFAULT_TRIALS);
+    let (mut died, mut settled, mut dynamic) = (0, 0, 0);
+    for trial in 0..trials {
+        let seed = first_seed.wrapping_add(trial);
+        simulation.reseed_from(seed, args.density);
+        let mut remaining = generations - period;
+        while remaining > 0 {
+            let batch = remaining.min(args.steps_per_submit as u64);
+            simulation.advance(batch as u32);
+            remaining -= batch;
+        }
+        // Only the last generations can be told apart from a cycle that started earlier.
+        let mut detector = CycleDetector::default();
+        for _ in 0..period {
+            detector.push(&simulation.read_cells());
+            simulation.update();
+        }
+        let cells = simulation.read_cells();
+        detector.push(&cells);
+        let population = cells.iter().filter(|&&cell| cell == 1).count();
+        if population == 0 {
+            died += 1;
+        } else if detector.is_settled() {
+            settled += 1;
+        } else {
+            println!("Seed {seed}: {population} cells still changing");
+            dynamic += 1;
+        }
+    }
+    println!(
+        "{dynamic} of {trials} soups still changing after {generations} generations, {settled} settled, {died} died out"
+    );
+    Ok(())
+}
+
+/// Compare every generation computed on the GPU with the CPU reference.
+#[cfg(not(target_arch = "wasm32"))]
+fn verify(simulation: &mut Simulation, args: &Args, generations: u64) -> anyhow::Result<()> {
+    let size = simulation.grid_size();
+    let mut expected = simulation.read_cells();
+    // Compare after every tick, so ticks of several generations check that the
+    // simulation ends up in the right buffer.
+    let mut generation = 0;
+    while generation < generations {
+        let tick = (args.steps_per_tick as u64).min(generations - generation);
+        simulation.advance(tick as u32);
+        for _ in 0..tick {
+            expected = reference::step_cpu(
+                &expected,
+                size.width,
+                size.height,
+                args.rule,
+                args.boundary,
+                args.neighborhood,
+            );
+        }
+        generation += tick;
+        let cells = simulation.read_cells();
+        if let Some(i) = (0..cells.len()).find(|&i| cells[i] != expected[i]) {
+            anyhow::bail!(
+                "Generation {generation} differs from the CPU reference at cell ({}, {}): \
+                 expected {}, got {}",
+                i % size.width,
+                i / size.width,
+                expected[i],
+                cells[i]
+            );
+        }
+    }
+
+    println!("{generations} generations match the CPU reference");
+    Ok(())
+}
+
+/// Run the [`FrameClock`] with a fixed frame cap and step interval, and the `--late-steps`
+/// policy, for a second of wall clock time. Like the event loop, it sleeps whenever the
+/// next frame isn't due yet. Then check that the frames and steps came at their rates: a
+/// clock that never waits would spin through far more frames, one that drifts would fall
+/// short.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_timing(late_steps: LateSteps) -> anyhow::Result<()> {
+    const MAX_FPS: u32 = 100;
+    const STEP_INTERVAL: Duration = Duration::from_millis(20);
+    const DURATION: Duration = Duration::from_secs(1);
+    // Allowed difference from the expected counts, for timers that wake up late.
+    const TOLERANCE: f64 = 0.1;
+
+    let mut clock = FrameClock::new(MAX_FPS, late_steps);
+    let (mut frames, mut steps) = (0u32, 0u32);
+    let start = Instant::now();
+    while start.elapsed() < DURATION {
+        let now = Instant::now();
+        if let ControlFlow::WaitUntil(next_frame) =
+            timing::control_flow(now, clock.next_frame(now), None, None)
+        {
+            std::thread::sleep(next_frame - now);
+            continue;
+        }
+        clock.start_frame(now, true);
+        frames += 1;
+        loop {
+            let batch = clock.take_steps(STEP_INTERVAL, u32::MAX);
+            if batch == 0 {
+                break;
+            }
+            steps += batch;
+        }
+    }
+
+    let seconds = DURATION.as_secs_f64();
+    for (name, count, expected) in [
+        ("frame rate", frames, MAX_FPS as f64 * seconds),
+        ("step rate", steps, seconds / STEP_INTERVAL.as_secs_f64()),
+    ] {
+        anyhow::ensure!(
+            (count as f64 - expected).abs() <= expected * TOLERANCE,
+            "The {name} is off, {count} in {DURATION:?} instead of {expected}"
+        );
+        println!("{name}: ok");
+    }
+    Ok(())
+}
+
+/// Run each of the [`GOLDENS`] from a fixed random start and compare the result with its
+/// snapshot, or overwrite the snapshots with `--bless`.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_goldens(
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    args: &Args,
+) -> anyhow::Result<()> {
+    let mut failures = 0;
+    for golden in &GOLDENS {
+        let name = golden.name;
+        let rule = golden.rule.parse().map_err(anyhow::Error::msg)?;
+        // The workgroup size and backend don't change the result, so they are checked too.
+        let settings = Settings {
+            workgroup_size: args.workgroup_size,
+            sim_backend: args.sim_backend,
+            ..golden.settings()
+        };
+        let mut simulation = Simulation::new(device.clone(), queue.clone(), &settings, rule, None)?;
+        simulation.advance(golden::GENERATIONS);
+        let cells = simulation.read_cells();
+
+        let path = golden.path();
+        let size = settings.grid_size;
+        if args.bless {
+            std::fs::create_dir_all(path.parent().expect("Goldens are in a directory"))
+                .and_then(|()| {
+                    snapshot::write(&path, size.width as u32, size.height as u32, &cells)
+                })
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("{name}: blessed {}", path.display());
+            continue;
+        }
+
+        let (_, _, expected) = snapshot::read(&path).with_context(|| {
+            format!(
+                "Failed to read {}, run with --bless to create it",
+                path.display()
+            )
+        })?;
+        let differences = (0..cells.len().min(expected.len()))
+            .filter(|&i| cells[i] != expected[i])
+            .count();
+        if cells.len() != expected.len() || differences > 0 {
+            println!(
+                "{name}: {differences} of {} cells differ from {}",
+                cells.len(),
+                path.display()
+            );
+            failures += 1;
+        } else {
+            println!("{name}: ok");
+        }
+    }
+    anyhow::ensure!(
+        failures == 0,
+        "{failures} of {} golden runs differ, run with --bless if the change is intended",
+        GOLDENS.len()
+    );
+    Ok(())
+}
+
+/// Time `generations` generations, recording `steps_per_submit` of them into each
+/// command encoder, and print the throughput.
+#[cfg(not(target_arch = "wasm32"))]
+fn benchmark(simulation: &mut Simulation, generations: u64, steps_per_submit: u32) {
+    let elapsed = time_generations(simulation, generations, steps_per_submit);
+
+    let grid_size = simulation.grid_size();
+    let generations_per_second = generations as f64 / elapsed.as_secs_f64();
+    let cells_per_second = generations_per_second * grid_size.cells() as f64;
+    println!("Grid {grid_size}, {generations} generations in {elapsed:.3?}");
+    println!("{generations_per_second:.1} generations/s, {cells_per_second:.3e} cells/s");
+    println!("Final population {}", simulation.population());
+}
+
+/// Advance `generations` generations in batches of `steps_per_submit` and wait for the
+/// GPU to finish them.
+#[cfg(not(target_arch = "wasm32"))]
+fn time_generations(
+    simulation: &mut Simulation,
+    generations: u64,
+    steps_per_submit: u32,
+) -> Duration {
+    let start = Instant::now();
+    let mut remaining = generations;
+    while remaining > 0 {
+        let batch = remaining.min(steps_per_submit as u64);
+        simulation.advance(batch as u32);
+        remaining -= batch;
+    }
+    simulation.device().poll(wgpu::Maintain::Wait);
+    start.elapsed()
+}
+
+/// Grid sides and workgroup sizes `--bench-matrix` times every combination of.
+#[cfg(not(target_arch = "wasm32"))]
+const BENCH_MATRIX_GRIDS: [usize; 4] = [256, 512, 1024, 2048];
+#[cfg(not(target_arch = "wasm32"))]
+const BENCH_MATRIX_WORKGROUPS: [u32; 4] = [4, 8, 16, 32];
+
+/// Time `generations` generations for every grid and workgroup size of the matrix that
+/// fits the device, with the other settings from `args`, and print the throughput of each
+/// and the fastest in cells per second, which is comparable across grid sizes.
+#[cfg(not(target_arch = "wasm32"))]
+fn bench_matrix(
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    args: &Args,
+    generations: u64,
+) -> anyhow::Result<()> {
+    println!(
+        "{:<11} {:>9} {:>14} {:>10}",
+        "grid", "workgroup", "generations/s", "cells/s"
+    );
+    let mut best: Option<(GridSize, u32, f64)> = None;
+    for side in BENCH_MATRIX_GRIDS {
+        for workgroup_size in BENCH_MATRIX_WORKGROUPS {
+            let mut settings = args.settings();
+            settings.grid_size = GridSize::square(side);
+            settings.workgroup_size = workgroup_size;
+            let grid = settings.grid_size.to_string();
+            if let Err(err) = simulation::check_limits(&device.limits(), &settings) {
+                println!("{grid:<11} {workgroup_size:>9} {:>14}", "skipped");
+                log::debug!("{err}");
+                continue;
             }
-            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
-                control_flow.set_wait_until(Instant::now() + UPDATE_INTERVAL);
-
-                // Slow render loop
-
-                // ```js
-                // const encoder = device.createCommandEncoder();
-                // ```
-                let mut encoder =
-                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-                let mut compute_pass =
-                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
-
-                compute_pass.set_pipeline(&simulation_pipeline);
-                compute_pass.set_bind_group(0, &bind_group[step], &[]);
-
-                let workgroup_count = (GRID_SIZE / WORKGROUP_SIZE) as u32;
-                compute_pass.dispatch_workgroups(workgroup_count, workgroup_count, 1);
-
-                drop(compute_pass);
-
-                // increment step
-                step = (step + 1) % 2;
-
-                // ```js
-                // const pass = encoder.beginRenderPass({
-                //     colorAttachments: [{
-                //         view: context.getCurrentTexture().createView(),
-                //         loadOp: "clear",
-                //         clearValue: { r: 0, g: 0, b: 0.4, a: 1 }, // New line
-                //         storeOp: "store",
-                //     }],
-                // });
-                // ```
-                let frame = surface
-                    .get_current_texture()
-                    .expect("Current texture not found");
-                let view = frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: None,
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 0.4,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-
-                pass.set_pipeline(&cell_pipeline);
-                pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                pass.set_bind_group(0, &bind_group[step], &[]);
-                let vs = (vertices.len() / 2) as u32;
-                let is: u32 = (GRID_SIZE * GRID_SIZE) as u32;
-                pass.draw(0..vs, 0..is);
-
-                // ```js
-                // pass.end()
-                // ```
-                drop(pass);
-
-                // ```js
-                // device.queue.submit([encoder.finish()]);
-                // ```
-                queue.submit(Some(encoder.finish()));
-
-                // Present the the work that has been submitted into the queue
-                frame.present();
+
+            let mut simulation =
+                Simulation::new(device.clone(), queue.clone(), &settings, args.rule, None)?;
+            // Compile the pipelines outside of the timed generations.
+            simulation.warm_up();
+            let elapsed = time_generations(&mut simulation, generations, args.steps_per_submit);
+            let generations_per_second = generations as f64 / elapsed.as_secs_f64();
+            let cells_per_second = generations_per_second * settings.grid_size.cells() as f64;
+            println!(
+                "{grid:<11} {workgroup_size:>9} {generations_per_second:>14.1} {cells_per_second:>10.3e}"
+            );
+            if best.is_none_or(|(_, _, fastest)| cells_per_second > fastest) {
+                best = Some((settings.grid_size, workgroup_size, cells_per_second));
             }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => *control_flow = ControlFlow::Exit,
-            _ => {}
         }
-    });
+    }
+
+    let (grid_size, workgroup_size, cells_per_second) =
+        best.context("None of the configurations fit the device")?;
+    println!(
+        "Fastest: grid {grid_size} with workgroup size {workgroup_size}, {cells_per_second:.3e} cells/s"
+    );
+    Ok(())
 }
 
-fn main() {
-    env_logger::init();
+#[cfg(not(target_arch = "wasm32"))]
+fn usage_error(err: &str) -> ! {
+    eprintln!("Error: {err}");
+    std::process::exit(2);
+}
+
+/// Log this crate at `info` unless `RUST_LOG`, `--verbose` or `--quiet` say otherwise, to
+/// stderr and with `--log-file` to that file too.
+#[cfg(not(target_arch = "wasm32"))]
+fn init_logger(level: Option<log::LevelFilter>, log_file: Option<&Path>) -> anyhow::Result<()> {
+    let mut builder = match level {
+        None => {
+            let env = env_logger::Env::default().default_filter_or("warn,your_first_wgpu_app=info");
+            env_logger::Builder::from_env(env)
+        }
+        Some(level) => {
+            // Debug output of wgpu itself is too much, only raise the level of this crate.
+            let mut builder = env_logger::Builder::new();
+            builder
+                .filter_level(level.min(log::LevelFilter::Warn))
+                .filter_module(module_path!(), level);
+            builder
+        }
+    };
+    if let Some(path) = log_file {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        builder.target(env_logger::Target::Pipe(Box::new(Tee(file))));
+    }
+    builder.init();
+    Ok(())
+}
+
+/// Writes the log to stderr and to the `--log-file`. The file isn't buffered, so the log
+/// up to a crash is kept.
+#[cfg(not(target_arch = "wasm32"))]
+struct Tee(std::fs::File);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::io::Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write_all(buf)?;
+        std::io::stderr().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()?;
+        std::io::stderr().flush()
+    }
+}
+
+/// Log the settings that matter for reproducing a run in one block.
+fn log_summary(
+    args: &Args,
+    adapter: &wgpu::AdapterInfo,
+    limits: &wgpu::Limits,
+    present_mode: Option<wgpu::PresentMode>,
+) {
+    log::info!("{}", summary(args, adapter, limits, present_mode));
+}
+
+/// The adapter, grid, rule and start of the run, one per line.
+fn summary(
+    args: &Args,
+    adapter: &wgpu::AdapterInfo,
+    limits: &wgpu::Limits,
+    present_mode: Option<wgpu::PresentMode>,
+) -> String {
+    let placed: Vec<_> = args
+        .placed_patterns
+        .iter()
+        .map(|(path, [x, y])| format!("{} at {x},{y}", path.display()))
+        .collect();
+    let start = match (&args.pattern, &args.load, &args.image) {
+        _ if !placed.is_empty() => format!("placed {}", placed.join(", ")),
+        (Some(path), _, _) | (_, Some(path), _) | (_, _, Some(path)) => {
+            format!("loaded from {}", path.display())
+        }
+        (None, None, None) => format!(
+            "density {}, seed {}",
+            args.density,
+            args.seed.expect("The seed is chosen at startup")
+        ),
+    };
+    let rule = match args.compare {
+        Some(compare) => format!("{} compared with {compare}", args.rule),
+        None => args.rule.to_string(),
+    };
+    let grid = args.grid_size;
+    // Not to be mistaken for the dead boundary, where the edge cells can live.
+    let boundary = match args.boundary {
+        Boundary::Border => format!(
+            "dead border around a {}x{} interior",
+            grid.width.saturating_sub(2),
+            grid.height.saturating_sub(2)
+        ),
+        boundary => format!("{boundary:?} boundary"),
+    };
+    let workgroup = args.workgroup_size;
+    let mut stepping = match args.sim_backend {
+        SimBackend::Gpu => format!("workgroups of {workgroup}x{workgroup}"),
+        SimBackend::Cpu => "stepped on the CPU".to_string(),
+    };
+    if args.state_buffers != 2 {
+        stepping += &format!(", {} state buffers", args.state_buffers);
+    }
+    let mut summary = format!(
+        "Configuration:
+  adapter        {}
+  limits         {}
+  grid           {grid}, {stepping}
+  rule           {rule} ({:?} neighborhood, {boundary})
+  initial state  {start}",
+        gpu::describe_adapter(adapter),
+        gpu::describe_limits(limits),
+        args.neighborhood,
+    );
+    if let Some(present_mode) = present_mode {
+        summary += &format!("\n  present mode   {present_mode:?}");
+    }
+    summary
+}
+
+/// Print the name, size and description of each built-in pattern, one per line.
+#[cfg(not(target_arch = "wasm32"))]
+fn list_patterns() {
+    for preset in pattern::PRESETS {
+        let pattern = Pattern::preset(preset.name).expect("Unknown built-in pattern");
+        let size = format!("{}x{}", pattern.width, pattern.height);
+        println!("{:<12} {size:<7} {}", preset.name, preset.description);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> anyhow::Result<()> {
+    let mut args = Args::parse().unwrap_or_else(|err| usage_error(&err));
+    init_logger(args.log_level, args.log_file.as_deref())?;
+    for warning in &args.warnings {
+        log::warn!("{warning}");
+    }
+    // Pick the seed here so it can be logged to replay the run with `--seed`.
+    args.seed.get_or_insert_with(rand::random);
+    if args.print_adapters {
+        gpu::print_adapters();
+        return Ok(());
+    }
+    if args.dump_config {
+        print!("{}", config::dump(&args));
+        return Ok(());
+    }
+    if args.list_patterns || args.pattern.as_deref() == Some(Path::new("list")) {
+        list_patterns();
+        return Ok(());
+    }
+    let initial_cells = load_initial_cells(&mut args).unwrap_or_else(|err| usage_error(&err));
+    if args.demo {
+        println!("Showing the Gosper glider gun, which fires a new glider every 30 generations");
+    }
+
+    if args.headless {
+        return pollster::block_on(run_headless(args, initial_cells));
+    }
 
-    const WINDOW_SIZE: u32 = 512;
     let event_loop = EventLoop::new();
     let window = winit::window::WindowBuilder::new()
-        .with_title("Your first wgpu app")
-        .with_inner_size(winit::dpi::PhysicalSize::new(WINDOW_SIZE, WINDOW_SIZE))
+        .with_title(TITLE)
+        .with_inner_size(winit::dpi::LogicalSize::new(
+            args.window_width,
+            args.window_height,
+        ))
         .build(&event_loop)
-        .unwrap();
+        .context("Failed to create the window")?;
+    pollster::block_on(run(event_loop, window, args, initial_cells))
+}
 
-    pollster::block_on(run(event_loop, window));
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::start();
 }
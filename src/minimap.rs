@@ -0,0 +1,129 @@
+//! The whole grid drawn small in a corner of the window, with the part the camera shows
+//! outlined, toggled with M.
+
+use crate::simulation::Simulation;
+
+/// Side of the minimap as a fraction of the shorter side of the viewport.
+const FRACTION: f32 = 0.25;
+/// Pixels between the minimap and the edges of the viewport.
+const MARGIN: f32 = 8.0;
+
+/// The pipeline that draws the minimap straight from the cell states, and the camera's
+/// view it outlines.
+pub struct Minimap {
+    pipeline: wgpu::RenderPipeline,
+    view_buffer: wgpu::Buffer,
+    view_bind_group: wgpu::BindGroup,
+}
+
+impl Minimap {
+    /// A minimap of the simulations on the device of `simulation`, drawn into textures
+    /// of `format` with `sample_count` samples per pixel.
+    pub fn new(simulation: &Simulation, format: wgpu::TextureFormat, sample_count: u32) -> Minimap {
+        let device = simulation.device();
+        let source = include_str!("shaders/minimap.wgsl")
+            .replace("${LINEAR_OUTPUT}", &format!("{}", format.is_srgb()));
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Minimap shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Minimap view layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        // The cells are read through the bind group the cell shader draws them with.
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Minimap pipeline layout"),
+            bind_group_layouts: &[simulation.render_bind_group_layout(), &view_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Minimap pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vertexMain",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fragmentMain",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Minimap view"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+        let view_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Minimap view bind group"),
+            layout: &view_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_buffer.as_entire_binding(),
+            }],
+        });
+
+        Minimap {
+            pipeline,
+            view_buffer,
+            view_bind_group,
+        }
+    }
+
+    /// Set the scale and offset of the grid in the window, to outline the part it shows.
+    pub fn set_view(&self, queue: &wgpu::Queue, view: [f32; 4]) {
+        queue.write_buffer(&self.view_buffer, 0, bytemuck::cast_slice(&view));
+    }
+
+    /// Record drawing the minimap of `simulation` into the top right corner of the viewport
+    /// at `x`, `y` of `width` by `height` pixels, with the same shape as the grid.
+    pub fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        simulation: &'a Simulation,
+        [x, y, width, height]: [f32; 4],
+    ) {
+        let grid_size = simulation.grid_size();
+        let longer = grid_size.width.max(grid_size.height) as f32;
+        let side = width.min(height) * FRACTION;
+        let size = [
+            side * grid_size.width as f32 / longer,
+            side * grid_size.height as f32 / longer,
+        ];
+        pass.set_viewport(
+            x + width - MARGIN - size[0],
+            y + MARGIN,
+            size[0],
+            size[1],
+            0.0,
+            1.0,
+        );
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, simulation.render_bind_group(), &[]);
+        pass.set_bind_group(1, &self.view_bind_group, &[]);
+        pass.draw(0..6, 0..1);
+    }
+}
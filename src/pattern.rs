@@ -0,0 +1,409 @@
+//! Patterns in the RLE and Life 1.06 formats shared by the Game of Life community.
+
+use crate::{
+    load_error::LoadError,
+    rule::Rule,
+    simulation::{Boundary, GridSize},
+};
+
+/// A built-in pattern, selected by its name with `--pattern`.
+pub struct Preset {
+    pub name: &'static str,
+    /// One line about the pattern, for `--list-patterns`.
+    pub description: &'static str,
+    pub rle: &'static str,
+}
+
+/// The built-in patterns, sorted by name.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "blinker",
+        description: "Period 2 oscillator, the smallest one",
+        rle: "x = 3, y = 1\n3o!",
+    },
+    Preset {
+        name: "block",
+        description: "Still life that never changes",
+        rle: "x = 2, y = 2\n2o$2o!",
+    },
+    Preset {
+        name: "glider",
+        description: "Spaceship that moves one cell diagonally every 4 generations",
+        rle: "x = 3, y = 3\nbo$2bo$3o!",
+    },
+    Preset {
+        name: "gosper-gun",
+        description: "Gosper glider gun, fires a glider every 30 generations",
+        rle:
+            "x = 36, y = 9
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    Preset {
+        name: "lwss",
+        description: "Lightweight spaceship, moves one cell sideways every 2 generations",
+        rle: "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!",
+    },
+    Preset {
+        name: "pulsar",
+        description: "Period 3 oscillator",
+        rle:
+            "x = 13, y = 13
+2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    Preset {
+        name: "toad",
+        description: "Period 2 oscillator",
+        rle: "x = 4, y = 2\nb3o$3o!",
+    },
+];
+
+/// The live cells of a pattern, with `(0, 0)` at the top left.
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>,
+    /// Rule given in the pattern file, if any.
+    pub rule: Option<Rule>,
+}
+
+impl Pattern {
+    /// Parse a pattern, detecting Life 1.06 by its `#Life 1.06` header and RLE otherwise.
+    pub fn parse(text: &str) -> Result<Pattern, LoadError> {
+        if text.trim_start().starts_with("#Life 1.06") {
+            parse_life_106(text)
+        } else {
+            parse_rle(text)
+        }
+    }
+
+    /// Look up one of the built-in [`PRESETS`] by name.
+    pub fn preset(name: &str) -> Option<Pattern> {
+        let preset = PRESETS.iter().find(|preset| preset.name == name)?;
+        Some(parse_rle(preset.rle).expect("Invalid built-in pattern"))
+    }
+
+    /// The live cells of a grid, stored row by row from the bottom, cropped to the
+    /// smallest rectangle that holds them all. Dying cells of Generations rules are
+    /// left out.
+    pub fn from_cells(cells: &[u32], grid_size: GridSize) -> Pattern {
+        let live: Vec<(usize, usize)> = cells
+            .iter()
+            .enumerate()
+            .filter(|&(_, &cell)| cell == 1)
+            .map(|(i, _)| {
+                let (col, row) = (i % grid_size.width, i / grid_size.width);
+                (col, grid_size.height - 1 - row)
+            })
+            .collect();
+        let left = live.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let top = live.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let width = live.iter().map(|&(x, _)| x + 1 - left).max().unwrap_or(0);
+        let height = live.iter().map(|&(_, y)| y + 1 - top).max().unwrap_or(0);
+        Pattern {
+            width,
+            height,
+            cells: live.iter().map(|&(x, y)| (x - left, y - top)).collect(),
+            rule: None,
+        }
+    }
+
+    /// Encode the pattern as RLE, with the rule in the header when there is one. Dead
+    /// cells at the end of a row are left out and lines are kept to 70 characters, as
+    /// other Life programs expect.
+    pub fn to_rle(&self) -> String {
+        let mut header = format!("x = {}, y = {}", self.width, self.height);
+        if let Some(rule) = self.rule {
+            header += &format!(", rule = {rule}");
+        }
+
+        let mut rows = vec![Vec::new(); self.height];
+        for &(x, y) in &self.cells {
+            rows[y].push(x);
+        }
+        let run = |count: usize, tag: char| match count {
+            1 => tag.to_string(),
+            count => format!("{count}{tag}"),
+        };
+        let mut items = Vec::new();
+        // Rows ended since the last live cell, written as a single run of `$`.
+        let mut row_ends = 0;
+        for row in &mut rows {
+            row.sort_unstable();
+            row.dedup();
+            if !row.is_empty() && row_ends > 0 {
+                items.push(run(row_ends, '$'));
+                row_ends = 0;
+            }
+            let mut x = 0;
+            for run_cells in row.chunk_by(|a, b| a + 1 == *b) {
+                let start = run_cells[0];
+                if start > x {
+                    items.push(run(start - x, 'b'));
+                }
+                items.push(run(run_cells.len(), 'o'));
+                x = start + run_cells.len();
+            }
+            row_ends += 1;
+        }
+        items.push("!".to_string());
+
+        let mut body = String::new();
+        let mut line_length = 0;
+        for item in items {
+            if line_length + item.len() > 70 {
+                body.push('\n');
+                line_length = 0;
+            }
+            body += &item;
+            line_length += item.len();
+        }
+        format!("{header}\n{body}\n")
+    }
+
+    /// Place the pattern in the center of an otherwise dead grid.
+    pub fn place(&self, grid_size: GridSize) -> Result<Vec<u32>, LoadError> {
+        if self.width > grid_size.width || self.height > grid_size.height {
+            return Err(LoadError::PatternTooLarge {
+                pattern: GridSize {
+                    width: self.width,
+                    height: self.height,
+                },
+                grid: grid_size,
+            });
+        }
+
+        let left = (grid_size.width - self.width) / 2;
+        let top = (grid_size.height - self.height) / 2;
+        let mut cells = vec![0u32; grid_size.cells()];
+        for &(x, y) in &self.cells {
+            // Row 0 of the grid is drawn at the bottom.
+            let row = grid_size.height - 1 - (top + y);
+            cells[row * grid_size.width + left + x] = 1;
+        }
+        Ok(cells)
+    }
+
+    /// Place the pattern in an otherwise dead grid with its top left corner `offset` cells
+    /// right of and below the top left corner of the grid. Cells that end up off the grid
+    /// wrap around it or are left out as the `boundary` does, as are those on the frame of
+    /// a bordered grid, and are counted in the returned number.
+    pub fn place_at(
+        &self,
+        grid_size: GridSize,
+        [left, top]: [isize; 2],
+        boundary: Boundary,
+    ) -> (Vec<u32>, usize) {
+        let (width, height) = (grid_size.width as isize, grid_size.height as isize);
+        let mut cells = vec![0u32; grid_size.cells()];
+        let mut outside = 0;
+        for &(x, y) in &self.cells {
+            let (mut x, mut y) = (left + x as isize, top + y as isize);
+            if !(0..width).contains(&x) || !(0..height).contains(&y) {
+                outside += 1;
+                match boundary {
+                    Boundary::Wrap => (x, y) = (x.rem_euclid(width), y.rem_euclid(height)),
+                    Boundary::Dead | Boundary::Border => continue,
+                }
+            } else if boundary.is_frame(x, y, width, height) {
+                outside += 1;
+                continue;
+            }
+            // Row 0 of the grid is drawn at the bottom.
+            let row = height - 1 - y;
+            cells[(row * width + x) as usize] = 1;
+        }
+        (cells, outside)
+    }
+}
+
+/// The lines of `text` that aren't empty or comments, trimmed, each with its line number
+/// and the column its first character was in, both from 1.
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+            (i + 1, indent + 1, line.trim())
+        })
+        .filter(|(_, _, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn syntax_error(line: usize, column: usize, message: String) -> LoadError {
+    LoadError::Syntax {
+        line,
+        column,
+        message,
+    }
+}
+
+fn parse_rle(text: &str) -> Result<Pattern, LoadError> {
+    let mut lines = content_lines(text);
+
+    // The header looks like `x = 3, y = 3, rule = B3/S23`.
+    let (line, column, header) = lines.next().ok_or_else(|| {
+        syntax_error(
+            text.lines().count() + 1,
+            1,
+            "missing RLE header".to_string(),
+        )
+    })?;
+    let invalid_header = || syntax_error(line, column, format!("invalid RLE header '{header}'"));
+    let (mut width, mut height, mut rule) = (None, None, None);
+    for field in header.split(',') {
+        let (key, value) = field.split_once('=').ok_or_else(invalid_header)?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = value.parse().ok(),
+            "y" => height = value.parse().ok(),
+            "rule" => {
+                rule = Some(
+                    value
+                        .parse::<Rule>()
+                        .map_err(|err| LoadError::UnsupportedRule(format!("'{value}': {err}")))?,
+                )
+            }
+            _ => {}
+        }
+    }
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(invalid_header());
+    };
+
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut count = String::new();
+    'lines: for (line, start, text) in lines {
+        for (i, c) in text.chars().enumerate() {
+            let column = start + i;
+            if c.is_ascii_digit() {
+                count.push(c);
+                continue;
+            } else if c.is_whitespace() {
+                continue;
+            }
+            let run = if count.is_empty() {
+                1
+            } else {
+                count.parse::<usize>().map_err(|_| {
+                    syntax_error(line, column, format!("run of {count} cells is too long"))
+                })?
+            };
+            // Whether a run of `run` from `from` ends past `size`, which no run can.
+            let past =
+                |from: usize, size: usize| from.checked_add(run).is_none_or(|end| end > size);
+            let too_large = || {
+                syntax_error(
+                    line,
+                    column,
+                    format!("RLE pattern is larger than its {width}x{height} header"),
+                )
+            };
+            match c {
+                'b' if past(x, width) => return Err(too_large()),
+                'b' => x += run,
+                'o' if past(x, width) || y >= height => return Err(too_large()),
+                'o' => {
+                    cells.extend((x..x + run).map(|x| (x, y)));
+                    x += run;
+                }
+                // Ending the last row with `$` is allowed, going below it isn't.
+                '$' if past(y, height) => return Err(too_large()),
+                '$' => {
+                    x = 0;
+                    y += run;
+                }
+                '!' => break 'lines,
+                c => {
+                    return Err(syntax_error(
+                        line,
+                        column,
+                        format!("unexpected '{c}' in RLE pattern"),
+                    ))
+                }
+            }
+            count.clear();
+        }
+    }
+
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+        rule,
+    })
+}
+
+fn parse_life_106(text: &str) -> Result<Pattern, LoadError> {
+    let mut coords = Vec::new();
+    for (line, column, text) in content_lines(text) {
+        let coord = text
+            .split_once(char::is_whitespace)
+            .and_then(|(x, y)| Some((x.parse::<i64>().ok()?, y.trim().parse::<i64>().ok()?)))
+            .ok_or_else(|| {
+                syntax_error(line, column, format!("invalid Life 1.06 line '{text}'"))
+            })?;
+        coords.push(coord);
+    }
+
+    // Life 1.06 coordinates are relative to an arbitrary origin, shift them to start at 0.
+    let min_x = coords.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = coords.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let cells: Vec<_> = coords
+        .iter()
+        .map(|&(x, y)| ((x - min_x) as usize, (y - min_y) as usize))
+        .collect();
+
+    Ok(Pattern {
+        width: cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0),
+        height: cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0),
+        cells,
+        rule: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pattern's size and sorted cells, to compare patterns without their rule.
+    fn shape(pattern: &Pattern) -> (usize, usize, Vec<(usize, usize)>) {
+        let mut cells = pattern.cells.clone();
+        cells.sort_unstable();
+        (pattern.width, pattern.height, cells)
+    }
+
+    #[test]
+    fn round_trips_through_rle() {
+        let grid_size = GridSize::square(48);
+        for name in ["block", "glider", "gosper-gun"] {
+            let preset = Pattern::preset(name).expect("Unknown built-in pattern");
+            let cells = preset.place(grid_size).expect("Preset doesn't fit");
+            let rle = Pattern::from_cells(&cells, grid_size).to_rle();
+            let parsed = Pattern::parse(&rle).expect("Failed to parse the written RLE");
+            assert_eq!(shape(&parsed), shape(&preset), "{name} in\n{rle}");
+        }
+    }
+
+    #[test]
+    fn empty_grid_has_no_size() {
+        let pattern = Pattern::from_cells(&[0; 16], GridSize::square(4));
+        assert_eq!(pattern.to_rle(), "x = 0, y = 0\n!\n");
+    }
+
+    #[test]
+    fn long_lines_are_wrapped() {
+        // Every other cell alive makes a row of `o` and 35 `bo`, 71 characters unwrapped.
+        let pattern = Pattern {
+            width: 71,
+            height: 1,
+            cells: (0..36).map(|x| (2 * x, 0)).collect(),
+            rule: None,
+        };
+        let rle = pattern.to_rle();
+        let body: Vec<&str> = rle.lines().skip(1).collect();
+        assert!(body.len() > 1, "Not wrapped:\n{rle}");
+        assert!(body.iter().all(|line| line.len() <= 70), "Too long:\n{rle}");
+        let parsed = Pattern::parse(&rle).expect("Failed to parse the wrapped RLE");
+        assert_eq!(shape(&parsed), shape(&pattern));
+    }
+}
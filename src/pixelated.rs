@@ -0,0 +1,135 @@
+//! Drawing the grid at one pixel per cell and scaling it up to the window with nearest
+//! filtering, for `--pixelated`.
+
+use crate::simulation::GridSize;
+
+/// The grid textures that the cells are drawn into and the pipeline that draws those
+/// textures into the window.
+pub struct Pixelated {
+    pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    // The camera's view, applied here instead of when drawing the cells.
+    view_buffer: wgpu::Buffer,
+    // One texture for each viewport, with the bind group that samples it.
+    targets: Vec<(wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl Pixelated {
+    /// Textures of one pixel per cell in `format`, for `viewports` simulations.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        grid_size: GridSize,
+        viewports: usize,
+    ) -> Pixelated {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "shaders/blit.wgsl"
+            ))),
+        });
+        // Let wgpu derive the bind group layout from the shader.
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vertexMain",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fragmentMain",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        // Nearest filtering keeps the edges between cells sharp at any zoom.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Grid sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Blit view"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        let mut pixelated = Pixelated {
+            pipeline,
+            sampler,
+            format,
+            view_buffer,
+            targets: Vec::new(),
+        };
+        pixelated.resize(device, grid_size, viewports);
+        pixelated
+    }
+
+    /// Replace the textures with ones for a grid of `grid_size` cells.
+    pub fn resize(&mut self, device: &wgpu::Device, grid_size: GridSize, viewports: usize) {
+        self.targets = (0..viewports)
+            .map(|_| {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Grid texture"),
+                    size: wgpu::Extent3d {
+                        width: grid_size.width as u32,
+                        height: grid_size.height as u32,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Blit bind group"),
+                    layout: &self.pipeline.get_bind_group_layout(0),
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.view_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                });
+                (view, bind_group)
+            })
+            .collect();
+    }
+
+    /// Set the scale and offset of the grid in the window.
+    pub fn set_view(&self, queue: &wgpu::Queue, view: [f32; 4]) {
+        queue.write_buffer(&self.view_buffer, 0, bytemuck::cast_slice(&view));
+    }
+
+    /// The texture to draw the cells of viewport `index` into.
+    pub fn target(&self, index: usize) -> &wgpu::TextureView {
+        &self.targets[index].0
+    }
+
+    /// Record drawing the texture of viewport `index` into the current viewport of `pass`.
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, index: usize) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.targets[index].1, &[]);
+        pass.draw(0..6, 0..1);
+    }
+}
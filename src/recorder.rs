@@ -0,0 +1,75 @@
+use std::{fs::File, io::BufWriter, path::Path, time::Duration};
+
+/// Frames larger than this along either side are downsampled to keep the file small.
+const MAX_SIZE: u32 = 256;
+
+/// Writes captured frames into an animated GIF.
+pub struct Recorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    // Downsampling factor applied to the captured frames.
+    scale: u32,
+    frames: u32,
+    total_frames: u32,
+}
+
+impl Recorder {
+    /// Create a recording of `total_frames` frames captured at `width` x `height`.
+    pub fn new(
+        path: &Path,
+        width: u32,
+        height: u32,
+        total_frames: u32,
+    ) -> Result<Recorder, gif::EncodingError> {
+        let scale = width.max(height).div_ceil(MAX_SIZE);
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder =
+            gif::Encoder::new(file, (width / scale) as u16, (height / scale) as u16, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Recorder {
+            encoder,
+            scale,
+            frames: 0,
+            total_frames,
+        })
+    }
+
+    /// Add an RGBA8 frame that is shown for `delay`. Returns true once all
+    /// frames are recorded.
+    pub fn add_frame(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        delay: Duration,
+    ) -> Result<bool, gif::EncodingError> {
+        // Nearest neighbour downsampling keeps the cell edges sharp.
+        let (scaled_width, scaled_height) = (width / self.scale, height / self.scale);
+        let mut scaled = Vec::with_capacity((scaled_width * scaled_height * 4) as usize);
+        for y in 0..scaled_height {
+            for x in 0..scaled_width {
+                let i = (((y * self.scale) * width + x * self.scale) * 4) as usize;
+                scaled.extend_from_slice(&pixels[i..i + 4]);
+            }
+        }
+
+        let mut frame =
+            gif::Frame::from_rgba_speed(scaled_width as u16, scaled_height as u16, &mut scaled, 10);
+        // GIF frame delays are in units of 10ms.
+        frame.delay = (delay.as_millis() / 10) as u16;
+        self.encoder.write_frame(&frame)?;
+
+        self.frames += 1;
+        if self.frames.is_multiple_of(10) {
+            log::info!("Recorded {}/{} frames", self.frames, self.total_frames);
+        }
+
+        Ok(self.frames >= self.total_frames)
+    }
+
+    /// Write the GIF trailer and close the file.
+    pub fn finish(self) -> std::io::Result<()> {
+        self.encoder.into_inner()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! A CPU implementation of the simulation, to check the compute shader against and to
+//! run with `--backend-sim cpu`.
+
+use crate::{
+    rule::Rule,
+    simulation::{Boundary, Neighborhood},
+};
+
+/// Compute the generation after `cells` the same way `simulation.wgsl` does.
+///
+/// Cells are stored row by row, `width` cells per row.
+pub fn step_cpu(
+    cells: &[u32],
+    width: usize,
+    height: usize,
+    rule: Rule,
+    boundary: Boundary,
+    neighborhood: Neighborhood,
+) -> Vec<u32> {
+    // Only live cells count, not dying ones.
+    let alive = |x: isize, y: isize| -> u32 {
+        let (w, h) = (width as isize, height as isize);
+        if boundary != Boundary::Wrap && (x < 0 || y < 0 || x >= w || y >= h) {
+            return 0;
+        }
+        (cells[(y.rem_euclid(h) * w + x.rem_euclid(w)) as usize] == 1) as u32
+    };
+
+    let mut next = vec![0; cells.len()];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            const ORTHOGONAL: [(isize, isize); 4] = [(1, 0), (0, -1), (-1, 0), (0, 1)];
+            const DIAGONAL: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+            let offsets = match neighborhood {
+                Neighborhood::Moore => &[ORTHOGONAL, DIAGONAL][..],
+                Neighborhood::VonNeumann => &[ORTHOGONAL][..],
+            };
+            let neighbors: u32 = offsets
+                .iter()
+                .flatten()
+                .map(|(dx, dy)| alive(x + dx, y + dy))
+                .sum();
+
+            let i = y as usize * width + x as usize;
+            if boundary.is_frame(x, y, width as isize, height as isize) {
+                continue;
+            }
+            next[i] = rule.next(cells[i], neighbors);
+        }
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cells of a `width` by `height` grid with those at `alive`, given as `(x, y)`,
+    /// alive.
+    fn grid(width: usize, height: usize, alive: &[(usize, usize)]) -> Vec<u32> {
+        let mut cells = vec![0; width * height];
+        for &(x, y) in alive {
+            cells[y * width + x] = 1;
+        }
+        cells
+    }
+
+    #[test]
+    fn blinker_turns() {
+        let horizontal = grid(5, 5, &[(1, 2), (2, 2), (3, 2)]);
+        let vertical = grid(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        for boundary in [Boundary::Wrap, Boundary::Dead] {
+            let next = step_cpu(
+                &horizontal,
+                5,
+                5,
+                Rule::default(),
+                boundary,
+                Neighborhood::Moore,
+            );
+            assert_eq!(next, vertical, "{boundary:?}");
+        }
+    }
+
+    #[test]
+    fn blinker_on_the_edge_wraps_only_on_a_torus() {
+        // Upright in the first column, so its horizontal phase needs the last column.
+        let blinker = grid(5, 5, &[(0, 1), (0, 2), (0, 3)]);
+        let step = |boundary| {
+            step_cpu(
+                &blinker,
+                5,
+                5,
+                Rule::default(),
+                boundary,
+                Neighborhood::Moore,
+            )
+        };
+        assert_eq!(step(Boundary::Wrap), grid(5, 5, &[(4, 2), (0, 2), (1, 2)]));
+        assert_eq!(step(Boundary::Dead), grid(5, 5, &[(0, 2), (1, 2)]));
+    }
+}
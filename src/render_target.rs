@@ -0,0 +1,86 @@
+//! The size and format of the frames drawn into a window's surface, which can shrink to
+//! nothing when the window is minimized.
+
+/// The configuration of a surface, and with more than one sample per pixel the
+/// multisampled texture the frame is drawn into and resolved from, kept the size of the
+/// surface.
+pub struct RenderTarget {
+    config: wgpu::SurfaceConfiguration,
+    // The window was resized to nothing, as when minimized on Windows, which
+    // makes the surface unusable until it is restored.
+    minimized: bool,
+    sample_count: u32,
+    msaa: Option<wgpu::TextureView>,
+}
+
+impl RenderTarget {
+    /// A target for frames of the size and format of `config`, the surface is expected to
+    /// be configured with it already.
+    pub fn new(
+        device: &wgpu::Device,
+        config: wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> RenderTarget {
+        let msaa = (sample_count > 1).then(|| create_msaa_target(device, &config, sample_count));
+        RenderTarget {
+            config,
+            minimized: false,
+            sample_count,
+            msaa,
+        }
+    }
+
+    /// Follow the window to `width` by `height` pixels, returning whether the surface has to
+    /// be configured with the new [`config`](RenderTarget::config). An empty size can't be
+    /// configured, so the target is marked minimized and keeps its last size instead.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> bool {
+        self.minimized = width == 0 || height == 0;
+        if self.minimized {
+            return false;
+        }
+
+        self.config.width = width;
+        self.config.height = height;
+        if self.msaa.is_some() {
+            self.msaa = Some(create_msaa_target(device, &self.config, self.sample_count));
+        }
+        true
+    }
+
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.config
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// The multisampled texture to draw into and resolve into the frame, if any.
+    pub fn msaa(&self) -> Option<&wgpu::TextureView> {
+        self.msaa.as_ref()
+    }
+}
+
+/// The multisampled texture the frame is drawn into with `--msaa`, the size of the surface.
+fn create_msaa_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
@@ -0,0 +1,271 @@
+//! Drawing the cells of a [`Simulation`] into any texture view, without a window.
+
+use crate::{gpu::byte_length, simulation::Simulation};
+
+/// How a live cell is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shape {
+    #[default]
+    Square,
+    Circle,
+}
+
+impl std::str::FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Shape::Square),
+            "circle" => Ok(Shape::Circle),
+            _ => Err("expected 'square' or 'circle'".to_string()),
+        }
+    }
+}
+
+/// Colors of live cells, cycled through with the C key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Gradient,
+    Terminal,
+    Fire,
+    Grayscale,
+}
+
+impl std::str::FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gradient" => Ok(Palette::Gradient),
+            "terminal" => Ok(Palette::Terminal),
+            "fire" => Ok(Palette::Fire),
+            "grayscale" => Ok(Palette::Grayscale),
+            _ => Err("expected 'gradient', 'terminal', 'fire' or 'grayscale'".to_string()),
+        }
+    }
+}
+
+impl Palette {
+    pub fn next(self) -> Palette {
+        match self {
+            Palette::Gradient => Palette::Terminal,
+            Palette::Terminal => Palette::Fire,
+            Palette::Fire => Palette::Grayscale,
+            Palette::Grayscale => Palette::Gradient,
+        }
+    }
+}
+
+/// The cell shader colors are given in sRGB, they are converted to linear light
+/// when drawing to an sRGB surface, which encodes them again.
+fn cell_shader_source(source: &str, format: wgpu::TextureFormat) -> String {
+    source.replace("${LINEAR_OUTPUT}", &format!("{}", format.is_srgb()))
+}
+
+fn create_cell_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: 8,
+        step_mode: wgpu::VertexStepMode::Vertex, // WebGPU defaults to `GPUVertexStepMode stepMode = "vertex";`
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Cell pipeline"),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: "vertexMain",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: "fragmentMain",
+            // Blend so the antialiased edges of circles fade into the background.
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        layout: Some(layout),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// The cell render pipeline and the quad it draws for each cell. One renderer draws any
+/// number of simulations on the same device, into targets of one texture format.
+pub struct Renderer {
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl Renderer {
+    /// A renderer for the simulations on the device of `simulation`, drawing into
+    /// textures of `format`.
+    pub fn new(simulation: &Simulation, format: wgpu::TextureFormat) -> Renderer {
+        Renderer::new_multisampled(simulation, format, 1)
+    }
+
+    /// A renderer like [`Renderer::new`] that draws into textures with `sample_count`
+    /// samples per pixel, to be resolved into the final target for smoother edges.
+    pub fn new_multisampled(
+        simulation: &Simulation,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Renderer {
+        let (device, queue) = (simulation.device(), simulation.queue());
+
+        // A unit quad, scaled and moved onto its cell in the shader.
+        #[rustfmt::skip]
+        let vertices: Vec<f32> = vec![
+            // X,  Y
+            0.0, 0.0,
+            1.0, 0.0,
+            1.0, 1.0,
+            0.0, 1.0,
+        ];
+        #[rustfmt::skip]
+        let indices: Vec<u16> = vec![
+            0, 1, 2, // Triangle 1
+            0, 2, 3, // Triangle 2
+        ];
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell vertices"),
+            size: byte_length(&vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        queue.write_buffer(&vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell indices"),
+            size: byte_length(&indices),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        queue.write_buffer(&index_buffer, 0, bytemuck::cast_slice(&indices));
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cell shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(cell_shader_source(
+                include_str!("shaders/cell.wgsl"),
+                format,
+            ))),
+        });
+        let pipeline = create_cell_pipeline(
+            device,
+            simulation.render_pipeline_layout(),
+            &module,
+            format,
+            sample_count,
+        );
+
+        Renderer {
+            pipeline,
+            format,
+            sample_count,
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    /// Format of the textures the renderer draws into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Samples per pixel of the textures the renderer draws into.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Record the compute pass that lists the cells of `simulation` to draw. It has to
+    /// be recorded before the render pass that draws them, each time they changed.
+    pub fn prepare(&self, encoder: &mut wgpu::CommandEncoder, simulation: &Simulation) {
+        simulation.cull(encoder, self.index_count);
+    }
+
+    /// Record drawing the cells of `simulation`, as listed by [`Renderer::prepare`], into
+    /// the current viewport of `pass`.
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, simulation: &'a Simulation) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_bind_group(0, simulation.render_bind_group(), &[]);
+        pass.draw_indexed_indirect(simulation.draw_args(), 0);
+    }
+
+    /// Record drawing the current generation of `simulation` into all of `view`, cleared
+    /// to `background` first.
+    pub fn render_to(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        simulation: &Simulation,
+        background: wgpu::Color,
+    ) {
+        self.prepare(encoder, simulation);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(background),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.draw(&mut pass, simulation);
+    }
+
+    /// Rebuild the pipeline from a changed cell shader, keeping the last good one on a
+    /// compile error.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shader(
+        &mut self,
+        simulation: &Simulation,
+        source: &str,
+    ) -> Result<(), wgpu::Error> {
+        let device = simulation.device();
+        crate::gpu::with_validation(device, || {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Cell shader"),
+                source: wgpu::ShaderSource::Wgsl(cell_shader_source(source, self.format).into()),
+            });
+            create_cell_pipeline(
+                device,
+                simulation.render_pipeline_layout(),
+                &module,
+                self.format,
+                self.sample_count,
+            )
+        })
+        .map(|pipeline| self.pipeline = pipeline)
+    }
+}
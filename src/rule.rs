@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+/// A Life-like rule in B/S notation, e.g. `B3/S23` for Conway's Game of Life,
+/// or a Generations rule in B/S/C notation, e.g. `B2/S/C3` for Brian's Brain.
+///
+/// Bit `i` of `birth` (`survive`) is set when a dead (live) cell with `i` live
+/// neighbors is alive in the next generation. Counts go up to 8 in the Moore
+/// neighborhood and up to 4 in the von Neumann neighborhood.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: u32,
+    pub survive: u32,
+    /// Number of cell states. With more than 2, a live cell that doesn't survive
+    /// goes through the dying states 2, 3, .. before it is dead (0) and can be
+    /// born again. Only live cells (1) count as neighbors.
+    pub states: u32,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            birth: 1 << 3,
+            survive: 1 << 2 | 1 << 3,
+            states: 2,
+        }
+    }
+}
+
+/// Rules that U cycles through, by name.
+pub const PRESETS: [(&str, &str); 4] = [
+    ("Conway", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Day & Night", "B3678/S34678"),
+    ("Seeds", "B2/S"),
+];
+
+impl Rule {
+    /// The name of the rule if it is one of the [`PRESETS`].
+    pub fn name(&self) -> Option<&'static str> {
+        PRESETS
+            .iter()
+            .find(|(_, rule)| rule.parse() == Ok(*self))
+            .map(|&(name, _)| name)
+    }
+
+    /// The preset after this rule, or the first one if it isn't a preset.
+    pub fn next_preset(&self) -> Rule {
+        let index = PRESETS
+            .iter()
+            .position(|(_, rule)| rule.parse() == Ok(*self))
+            .map_or(0, |index| (index + 1) % PRESETS.len());
+        PRESETS[index].1.parse().expect("Invalid preset rule")
+    }
+
+    /// The state of a cell in the next generation, given its state and its number of live neighbors.
+    pub fn next(&self, state: u32, neighbors: u32) -> u32 {
+        match state {
+            0 => (self.birth >> neighbors) & 1,
+            1 if (self.survive >> neighbors) & 1 == 1 => 1,
+            // Start or continue dying, dying cells are dead after the last state.
+            state => (state + 1) % self.states,
+        }
+    }
+}
+
+fn parse_counts(counts: &str) -> Option<u32> {
+    counts.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+        Some(n) if n <= 8 => Some(mask | 1 << n),
+        _ => None,
+    })
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || "expected B/S notation like B3/S23 or B/S/C like B2/S/C3".to_string();
+        let (birth, survive) = s.split_once('/').ok_or_else(err)?;
+        let (survive, states) = match survive.split_once('/') {
+            Some((survive, states)) => {
+                let states = states
+                    .strip_prefix(['C', 'c'])
+                    .and_then(|states| states.parse().ok())
+                    .filter(|&states| states >= 2)
+                    .ok_or_else(err)?;
+                (survive, states)
+            }
+            None => (survive, 2),
+        };
+        let birth = birth
+            .strip_prefix(['B', 'b'])
+            .and_then(parse_counts)
+            .ok_or_else(err)?;
+        let survive = survive
+            .strip_prefix(['S', 's'])
+            .and_then(parse_counts)
+            .ok_or_else(err)?;
+        Ok(Rule {
+            birth,
+            survive,
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u32| -> String {
+            (0..=8)
+                .filter(|n| mask & 1 << n != 0)
+                .map(|n| char::from_digit(n, 10).unwrap())
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survive))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
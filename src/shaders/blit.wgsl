@@ -0,0 +1,31 @@
+struct VertexOutput {
+    @builtin(position) pos: vec4f,
+    @location(0) uv: vec2f,
+};
+
+// Scale in xy and offset in zw of the grid in clip space, as for the cell shader.
+@group(0) @binding(0) var<uniform> view: vec4f;
+@group(0) @binding(1) var grid_texture: texture_2d<f32>;
+@group(0) @binding(2) var grid_sampler: sampler;
+
+// Draw the grid texture as a quad where the cell shader would draw the grid, from two
+// triangles without a vertex buffer.
+@vertex
+fn vertexMain(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2f(-1.0, -1.0), vec2f(1.0, -1.0), vec2f(1.0, 1.0),
+        vec2f(-1.0, -1.0), vec2f(1.0, 1.0), vec2f(-1.0, 1.0),
+    );
+    let corner = corners[index];
+
+    var output: VertexOutput;
+    output.pos = vec4f(corner * view.xy + view.zw, 0.0, 1.0);
+    // The top row of the texture is at the top of the grid.
+    output.uv = vec2f(corner.x + 1.0, 1.0 - corner.y) / 2.0;
+    return output;
+}
+
+@fragment
+fn fragmentMain(input: VertexOutput) -> @location(0) vec4f {
+    return textureSample(grid_texture, grid_sampler, input.uv);
+}
@@ -0,0 +1,263 @@
+struct VertexInput {
+    // Corner of the unit quad, from 0 to 1.
+    @location(0) pos: vec2f,
+    @builtin(instance_index) instance: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) pos: vec4f,
+    @location(0) cell: vec2f,
+    @location(1) age: f32,
+    @location(2) state: f32,
+    // Position inside the cell quad, from -1 to 1.
+    @location(3) local: vec2f,
+    // From 1 for just died to 0 for dead, for Generations rules.
+    @location(4) dying: f32,
+    // From 1 for just died fading to 0, when trails are enabled.
+    @location(5) trail: f32,
+    // Live neighbors, only set for the heatmap.
+    @location(6) neighbors: f32,
+};
+
+struct Uniforms {
+    grid: vec2f,
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    color_by_age: u32,
+    // Fraction of each cell left empty around the quad.
+    cell_gap: f32,
+    grid_lines: u32,
+    // Scale in xy and offset in zw of the grid in clip space. The scale includes
+    // the window aspect ratio, so cells stay square.
+    view: vec4f,
+    shape: u32,
+    // Fade between the previous and current generation by `progress`.
+    interpolate: u32,
+    progress: f32,
+    custom_color: u32,
+    alive_color: vec4f,
+    palette: u32,
+    neighborhood: u32,
+    // More than 2 for Generations rules, where states from 2 up are dying cells.
+    states: u32,
+    // Tint live cells on the edges of a wrapping grid, where they neighbor the opposite edge.
+    show_wrap: u32,
+    // Trails are drawn when dead cells fade out by this factor, 1 disables them.
+    trail_decay: f32,
+    // Direction the position based palettes run in.
+    gradient: u32,
+    // Color every cell by its number of live neighbors instead.
+    heatmap: u32,
+    // Draw dead cells as faint ghosts of their live color, without grid lines.
+    show_dead: u32,
+    // Swap every color for its opposite, after `high_contrast`.
+    invert: u32,
+    // Draw live cells in white and nothing else, over a black background.
+    high_contrast: u32,
+};
+
+const SHAPE_CIRCLE: u32 = 1u;
+// Outside of clip space, where instances that aren't drawn are moved to.
+const HIDDEN: vec4f = vec4f(2.0, 2.0, 2.0, 1.0);
+const BOUNDARY_WRAP: u32 = 0u;
+const NEIGHBORHOOD_VON_NEUMANN: u32 = 1u;
+// Opacity of dead cells over the background with `show_dead`.
+const GHOST_ALPHA: f32 = 0.1;
+// Trails fainter than 1 / 256, which an 8 bit color doesn't show, are not drawn.
+const TRAIL_MIN: f32 = 0.00390625;
+// Whether the surface is sRGB and expects linear colors, set when the shader is loaded.
+const LINEAR_OUTPUT: bool = ${LINEAR_OUTPUT};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage> cell_state: array<u32>;
+@group(0) @binding(2) var<storage> previous_cell_state: array<u32>;
+@group(0) @binding(3) var<storage> cell_age: array<u32>;
+@group(0) @binding(4) var<storage> cell_trail: array<f32>;
+// Indices of the cells to draw, one per instance, written by the cull shader.
+@group(0) @binding(5) var<storage> visible_cells: array<u32>;
+// Live neighbors of each cell, written by the simulation shader's `countMain` for the heatmap.
+@group(0) @binding(6) var<storage> neighbor_count: array<u32>;
+
+@vertex
+fn vertexMain(input: VertexInput) -> VertexOutput {
+
+    let grid = uniforms.grid;
+    let index = visible_cells[input.instance];
+    let i = f32(index);
+    let cell = vec2f(i % grid.x, floor(i / grid.x));
+    let value = cell_state[index];
+    var state = f32(value == 1u);
+    if (uniforms.interpolate == 1u) {
+        // Births grow in and deaths shrink out.
+        state = mix(f32(previous_cell_state[index] == 1u), state, uniforms.progress);
+    }
+    let dying = select(0.0, 1.0 - f32(value - 1u) / f32(uniforms.states - 1u), value >= 2u);
+
+    var trail = 0.0;
+    if (uniforms.trail_decay < 1.0 && cell_trail[index] > TRAIL_MIN) {
+        trail = cell_trail[index];
+    }
+
+    // Dead cells aren't drawn, unless they show the grid lines, the heatmap or their ghost.
+    // Cells being born or dying grow and shrink with `state` while interpolating, or fade
+    // in and out as they are always drawn at full size.
+    let size = max(max(state, f32(value >= 2u)), f32(trail > 0.0));
+    let full = uniforms.grid_lines == 1u || uniforms.heatmap == 1u || uniforms.show_dead == 1u;
+    let scale = select(size, 1.0, full) * (1.0 - uniforms.cell_gap);
+
+    // The quad is scaled around the center of its cell, in cells, and then mapped from
+    // the grid to -1 to 1 and on through the view.
+    let cell_pos = cell + 0.5 + (input.pos - 0.5) * scale;
+    let grid_pos = cell_pos / grid * 2.0 - 1.0;
+
+    var output: VertexOutput;
+    output.pos = select(HIDDEN, vec4f(grid_pos * uniforms.view.xy + uniforms.view.zw, 0.0, 1.0), scale > 0.0);
+    output.cell = cell;
+    output.age = f32(cell_age[index]);
+    output.state = state;
+    output.local = input.pos * 2.0 - 1.0;
+    output.dying = dying;
+    output.trail = trail;
+    if (uniforms.heatmap == 1u) {
+        output.neighbors = f32(neighbor_count[index]);
+    }
+    return output;
+}
+
+@fragment
+fn fragmentMain(input: VertexOutput) -> @location(0) vec4f {
+    var color = cell_color(input);
+    if (uniforms.high_contrast == 1u) {
+        color = vec4f(1.0, 1.0, 1.0, color.a * input.state);
+    }
+    if (uniforms.invert == 1u) {
+        color = vec4f(1.0 - color.rgb, color.a);
+    }
+
+    // Cut a disc out of the quad, fading the edge over about a pixel.
+    if (uniforms.shape == SHAPE_CIRCLE) {
+        let distance = length(input.local);
+        let edge = fwidth(distance);
+        if (distance > 1.0) {
+            discard;
+        }
+        color.a *= 1.0 - smoothstep(1.0 - edge, 1.0, distance);
+    }
+
+    if (LINEAR_OUTPUT) {
+        color = vec4f(srgb_to_linear(color.rgb), color.a);
+    }
+    return color;
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
+
+fn cell_color(input: VertexOutput) -> vec4f {
+    if (uniforms.heatmap == 1u) {
+        return heatmap_color(input);
+    }
+    // Dead cells are only drawn to show the grid lines, as dim tiles.
+    let dead = vec4f(0.1, 0.1, 0.15, 1.0);
+    if (input.dying > 0.0) {
+        // Dying cells fade from blue to the dead color.
+        return mix(dead, vec4f(0.3, 0.5, 1.0, 1.0), input.dying);
+    }
+    var live = live_color(input);
+    if (on_wrapped_edge(input.cell)) {
+        live = vec4f(mix(live.rgb, vec3f(1.0), 0.3), live.a);
+    }
+    var base = dead;
+    var glow = input.state;
+    if (input.trail > 0.0) {
+        // Recently dead cells glow in their live color as the trail fades. Without grid
+        // lines they fade into the background instead of into the dead tile.
+        base = select(vec4f(live.rgb, 0.0), dead, uniforms.grid_lines == 1u);
+        glow = max(glow, input.trail * 0.6);
+    }
+    if (uniforms.show_dead == 1u && uniforms.grid_lines == 0u) {
+        base = vec4f(live.rgb, GHOST_ALPHA);
+    }
+    return mix(base, live, glow);
+}
+
+// From dark blue for no live neighbors through red to yellow for all of them. Live cells
+// are brighter than dead ones, so the pattern stays visible.
+fn heatmap_color(input: VertexOutput) -> vec4f {
+    let most = select(8.0, 4.0, uniforms.neighborhood == NEIGHBORHOOD_VON_NEUMANN);
+    let heat = input.neighbors / most;
+    let none = vec3f(0.05, 0.05, 0.3);
+    let some = vec3f(0.9, 0.1, 0.1);
+    let all = vec3f(1.0, 0.95, 0.3);
+    let color = mix(mix(none, some, min(heat * 2.0, 1.0)), all, max(heat * 2.0 - 1.0, 0.0));
+    return vec4f(color * mix(0.35, 1.0, input.state), 1.0);
+}
+
+fn on_wrapped_edge(cell: vec2f) -> bool {
+    if (uniforms.show_wrap == 0u || uniforms.boundary != BOUNDARY_WRAP) {
+        return false;
+    }
+    // The cell coordinates are whole numbers, compared with some margin as they are interpolated.
+    return any(cell < vec2f(0.5)) || any(cell > uniforms.grid - 1.5);
+}
+
+// Position of a cell from 0 to 1 along the gradient, in both components for the
+// palettes that use only one of them.
+fn gradient_position(cell: vec2f) -> vec2f {
+    let c = cell / uniforms.grid;
+    switch uniforms.gradient {
+        // Left to right.
+        case 1u: {
+            return vec2f(c.x);
+        }
+        // Bottom to top.
+        case 2u: {
+            return vec2f(c.y);
+        }
+        // Out from the center, reaching 1 in the corners.
+        case 3u: {
+            return vec2f(length(c - 0.5) / length(vec2f(0.5)));
+        }
+        // From the bottom left to the top right corner.
+        default: {
+            return c;
+        }
+    }
+}
+
+fn live_color(input: VertexOutput) -> vec4f {
+    if (uniforms.custom_color == 1u) {
+        return uniforms.alive_color;
+    }
+
+    if (uniforms.color_by_age == 1u) {
+        // Young cells are blue, turning red and then yellow as they survive.
+        let heat = 1.0 - exp(-input.age / 16.0);
+        let cold = vec3f(0.2, 0.5, 1.0);
+        let hot = vec3f(1.0, 0.2, 0.0);
+        let glow = vec3f(1.0, 0.9, 0.2);
+        return vec4f(mix(mix(cold, hot, min(heat * 2.0, 1.0)), glow, max(heat * 2.0 - 1.0, 0.0)), 1.0);
+    }
+
+    let c = gradient_position(input.cell);
+    switch uniforms.palette {
+        // Terminal green.
+        case 1u: {
+            return vec4f(0.2, 1.0, 0.3, 1.0);
+        }
+        // Fire, from red at the bottom to yellow at the top.
+        case 2u: {
+            return vec4f(1.0, mix(0.1, 0.9, c.y), 0.0, 1.0);
+        }
+        // Grayscale, brightening towards the top right.
+        case 3u: {
+            return vec4f(vec3f(mix(0.4, 1.0, (c.x + c.y) / 2.0)), 1.0);
+        }
+        // The gradient from the codelab.
+        default: {
+            return vec4f(c, 1.0-c.x, 1.0);
+        }
+    }
+}
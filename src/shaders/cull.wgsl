@@ -0,0 +1,70 @@
+struct Uniforms {
+    grid: vec2f,
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    color_by_age: u32,
+    cell_gap: f32,
+    grid_lines: u32,
+    view: vec4f,
+    shape: u32,
+    interpolate: u32,
+    progress: f32,
+    custom_color: u32,
+    alive_color: vec4f,
+    palette: u32,
+    neighborhood: u32,
+    states: u32,
+    show_wrap: u32,
+    trail_decay: f32,
+    gradient: u32,
+    heatmap: u32,
+    show_dead: u32,
+    invert: u32,
+    high_contrast: u32,
+};
+
+// The arguments of `draw_indexed_indirect`.
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+// Trails fainter than 1 / 256 are not drawn, matching the cell shader.
+const TRAIL_MIN: f32 = 0.00390625;
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage> cell_state: array<u32>;
+@group(0) @binding(2) var<storage> previous_cell_state: array<u32>;
+@group(0) @binding(3) var<storage> cell_trail: array<f32>;
+@group(0) @binding(4) var<storage, read_write> visible_cells: array<u32>;
+@group(0) @binding(5) var<storage, read_write> draw_args: DrawArgs;
+
+// Whether the cell shader draws anything for cell `i`, see the `size` of a cell in `cell.wgsl`.
+fn is_visible(i: u32) -> bool {
+    return uniforms.grid_lines == 1u
+        || uniforms.heatmap == 1u
+        || uniforms.show_dead == 1u
+        || cell_state[i] != 0u
+        || (uniforms.interpolate == 1u && previous_cell_state[i] == 1u)
+        || (uniforms.trail_decay < 1.0 && cell_trail[i] > TRAIL_MIN);
+}
+
+// Append the index of each visible cell to `visible_cells`, counting them in the
+// instance count of the draw. The order of the cells doesn't matter, as they don't overlap.
+@compute
+@workgroup_size(8, 8)
+fn cullMain(@builtin(global_invocation_id) cell: vec3u) {
+    let grid = vec2u(uniforms.grid);
+    if (cell.x >= grid.x || cell.y >= grid.y) {
+        return;
+    }
+
+    let i = cell.y * grid.x + cell.x;
+    if (is_visible(i)) {
+        visible_cells[atomicAdd(&draw_args.instance_count, 1u)] = i;
+    }
+}
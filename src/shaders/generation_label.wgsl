@@ -0,0 +1,56 @@
+struct Label {
+    // Digits of the generation, most significant first, four to a vector.
+    digits: array<vec4u, 5>,
+    count: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) pos: vec4f,
+    // Position in the label, from 0 to 1 left to right and top to bottom.
+    @location(0) uv: vec2f,
+};
+
+@group(0) @binding(0) var<uniform> label: Label;
+// The glyphs of the digits 0 to 9 side by side, one texel per font pixel.
+@group(0) @binding(1) var atlas: texture_2d<f32>;
+
+const GLYPH_WIDTH: u32 = ${GLYPH_WIDTH}u;
+const GLYPH_HEIGHT: u32 = ${GLYPH_HEIGHT}u;
+// Font pixels from one digit to the next, a glyph and a column of space, and around the
+// digits.
+const ADVANCE: u32 = ${ADVANCE}u;
+const PADDING: u32 = 1u;
+
+// The whole viewport, which is the corner the label is drawn in, from two triangles
+// without a vertex buffer.
+@vertex
+fn vertexMain(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2f(0.0, 1.0), vec2f(1.0, 1.0), vec2f(1.0, 0.0),
+        vec2f(0.0, 1.0), vec2f(1.0, 0.0), vec2f(0.0, 0.0),
+    );
+    let corner = corners[index];
+
+    var output: VertexOutput;
+    output.pos = vec4f(corner.x * 2.0 - 1.0, 1.0 - corner.y * 2.0, 0.0, 1.0);
+    output.uv = corner;
+    return output;
+}
+
+@fragment
+fn fragmentMain(input: VertexOutput) -> @location(0) vec4f {
+    let size = vec2f(f32(label.count * ADVANCE - 1u + 2u * PADDING), f32(GLYPH_HEIGHT + 2u * PADDING));
+    let pixel = vec2i(floor(input.uv * size)) - i32(PADDING);
+
+    var lit = 0.0;
+    if (pixel.x >= 0 && pixel.y >= 0 && pixel.y < i32(GLYPH_HEIGHT)) {
+        let index = u32(pixel.x) / ADVANCE;
+        let x = u32(pixel.x) % ADVANCE;
+        if (index < label.count && x < GLYPH_WIDTH) {
+            let digit = label.digits[index / 4u][index % 4u];
+            lit = textureLoad(atlas, vec2u(digit * GLYPH_WIDTH + x, u32(pixel.y)), 0).r;
+        }
+    }
+    // White and black are the same in linear and sRGB targets.
+    return mix(vec4f(0.0, 0.0, 0.0, 0.6), vec4f(1.0), lit);
+}
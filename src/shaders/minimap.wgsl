@@ -0,0 +1,77 @@
+// Only the start of the `Uniforms` struct of the cell shader, the rest isn't needed.
+struct Uniforms {
+    grid: vec2f,
+};
+
+struct VertexOutput {
+    @builtin(position) pos: vec4f,
+    // Position on the grid, from -1 to 1 in both directions as in the cell shader.
+    @location(0) grid_pos: vec2f,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage> cell_state: array<u32>;
+// Scale in xy and offset in zw of the grid in the window, as set by the camera.
+@group(1) @binding(0) var<uniform> camera: vec4f;
+
+const LINEAR_OUTPUT: bool = ${LINEAR_OUTPUT};
+// Cells sampled along each side of a pixel, so lone cells still show on large grids.
+const SAMPLES: i32 = 4;
+
+// The whole viewport, which is the corner the minimap is drawn in, from two triangles
+// without a vertex buffer.
+@vertex
+fn vertexMain(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2f(-1.0, -1.0), vec2f(1.0, -1.0), vec2f(1.0, 1.0),
+        vec2f(-1.0, -1.0), vec2f(1.0, 1.0), vec2f(-1.0, 1.0),
+    );
+    let corner = corners[index];
+
+    var output: VertexOutput;
+    output.pos = vec4f(corner, 0.0, 1.0);
+    output.grid_pos = corner;
+    return output;
+}
+
+fn alive(cell: vec2i) -> f32 {
+    let grid = vec2i(uniforms.grid);
+    let clamped = clamp(cell, vec2i(0), grid - 1);
+    return f32(cell_state[clamped.y * grid.x + clamped.x] == 1u);
+}
+
+@fragment
+fn fragmentMain(input: VertexOutput) -> @location(0) vec4f {
+    // Row 0 of the grid is at the bottom, as in the window.
+    let cell = (input.grid_pos + 1.0) / 2.0 * uniforms.grid;
+    let footprint = max(fwidth(cell), vec2f(1.0));
+    let line = fwidth(input.grid_pos) * 0.75;
+
+    var live = 0.0;
+    for (var y = 0; y < SAMPLES; y++) {
+        for (var x = 0; x < SAMPLES; x++) {
+            let offset = (vec2f(f32(x), f32(y)) + 0.5) / f32(SAMPLES) - 0.5;
+            live = max(live, alive(vec2i(floor(cell + offset * footprint))));
+        }
+    }
+    var color = vec4f(mix(vec3f(0.05, 0.05, 0.1), vec3f(0.6, 1.0, 0.6), live), 0.85);
+
+    // Outline the part of the grid that is in the window.
+    let low = (-1.0 - camera.zw) / camera.xy;
+    let high = (1.0 - camera.zw) / camera.xy;
+    let p = input.grid_pos;
+    let outer = all(p > low - line) && all(p < high + line);
+    let inner = all(p > low + line) && all(p < high - line);
+    if (outer && !inner) {
+        color = vec4f(1.0, 0.85, 0.2, 1.0);
+    }
+
+    if (LINEAR_OUTPUT) {
+        color = vec4f(srgb_to_linear(color.rgb), color.a);
+    }
+    return color;
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
@@ -0,0 +1,43 @@
+struct SeedParams {
+    grid: vec2u,
+    seed: u32,
+    // Probability of a cell starting out alive.
+    density: f32,
+    // 0 for none, 1 to mirror the left half and 2 to mirror the top left quarter.
+    symmetry: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: SeedParams;
+@group(0) @binding(1) var<storage, read_write> cell_state: array<u32>;
+
+// PCG hash from "Hash Functions for GPU Rendering" by Jarzynski and Olano.
+fn hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// The cell whose random state `cell` takes, as `Symmetry::source` on the CPU.
+fn source(cell: vec2u) -> vec2u {
+    let mirrored = min(cell, params.grid - 1u - cell);
+    switch params.symmetry {
+        case 1u: { return vec2u(mirrored.x, cell.y); }
+        case 2u: { return mirrored; }
+        default: { return cell; }
+    }
+}
+
+@compute
+@workgroup_size(8, 8)
+fn seedMain(@builtin(global_invocation_id) cell: vec3u) {
+    if (cell.x >= params.grid.x || cell.y >= params.grid.y) {
+        return;
+    }
+
+    let i = cell.y * params.grid.x + cell.x;
+    let mirrored = source(cell.xy);
+
+    // Use the top 24 bits, which a f32 holds exactly, as a number in 0..1.
+    let random = f32(hash((mirrored.y * params.grid.x + mirrored.x) ^ hash(params.seed)) >> 8u) / 16777216.0;
+    cell_state[i] = u32(random < params.density);
+}
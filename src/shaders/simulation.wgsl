@@ -0,0 +1,147 @@
+struct Uniforms {
+    grid: vec2f,
+    // Bit i is set when i neighbors cause a birth or survival.
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    color_by_age: u32,
+    // Fraction of each cell left empty around the quad.
+    cell_gap: f32,
+    grid_lines: u32,
+    // Scale in xy and offset in zw of the grid in clip space, only used for drawing.
+    view: vec4f,
+    shape: u32,
+    interpolate: u32,
+    progress: f32,
+    custom_color: u32,
+    alive_color: vec4f,
+    palette: u32,
+    neighborhood: u32,
+    // More than 2 for Generations rules, where states from 2 up are dying cells.
+    states: u32,
+    // Only used for drawing.
+    show_wrap: u32,
+    // Factor the trail of dead cells fades by each generation.
+    trail_decay: f32,
+    gradient: u32,
+    heatmap: u32,
+    show_dead: u32,
+    invert: u32,
+    high_contrast: u32,
+};
+
+const BOUNDARY_WRAP: u32 = 0u;
+const BOUNDARY_BORDER: u32 = 2u;
+const NEIGHBORHOOD_VON_NEUMANN: u32 = 1u;
+const FIXED_DEAD: u32 = 1u;
+const FIXED_ALIVE: u32 = 2u;
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage> cell_state_in: array<u32>;
+@group(0) @binding(2) var<storage, read_write> cell_state_out: array<u32>;
+// Generations each cell has been continuously alive.
+@group(0) @binding(3) var<storage> cell_age_in: array<u32>;
+@group(0) @binding(4) var<storage, read_write> cell_age_out: array<u32>;
+// Afterimage of live cells, from 1 while alive fading towards 0 after they die.
+@group(0) @binding(5) var<storage> cell_trail_in: array<f32>;
+@group(0) @binding(6) var<storage, read_write> cell_trail_out: array<f32>;
+// Live neighbors of each cell of the input generation, only written for the heatmap.
+@group(0) @binding(7) var<storage, read_write> neighbor_count: array<u32>;
+// Cells held dead or alive every generation whatever the rule says, 0 for the others.
+@group(0) @binding(8) var<storage> fixed_cells: array<u32>;
+
+fn cell_index(cell: vec2<i32>) -> u32 {
+    return u32(
+        ((cell.y + i32(uniforms.grid.y)) % i32(uniforms.grid.y)) * i32(uniforms.grid.x) +
+        ((cell.x + i32(uniforms.grid.x)) % i32(uniforms.grid.x))
+    );
+}
+
+fn cell_active(x: i32, y: i32) -> u32 {
+    // Off-grid neighbors are dead unless the grid wraps around.
+    let grid = vec2i(uniforms.grid);
+    if (uniforms.boundary != BOUNDARY_WRAP && (x < 0 || y < 0 || x >= grid.x || y >= grid.y)) {
+        return 0u;
+    }
+    // Dying cells don't count.
+    return u32(cell_state_in[cell_index(vec2(x, y))] == 1u);
+}
+
+// Determine how many active neighbors a cell has.
+fn count_neighbors(cell: vec2i) -> u32 {
+    var active_neighbors = cell_active(cell.x + 1, cell.y) +
+                           cell_active(cell.x,     cell.y - 1) +
+                           cell_active(cell.x - 1, cell.y) +
+                           cell_active(cell.x,     cell.y + 1);
+    // The diagonal neighbors only count in the Moore neighborhood.
+    if (uniforms.neighborhood != NEIGHBORHOOD_VON_NEUMANN) {
+        active_neighbors += cell_active(cell.x + 1, cell.y + 1) +
+                            cell_active(cell.x + 1, cell.y - 1) +
+                            cell_active(cell.x - 1, cell.y - 1) +
+                            cell_active(cell.x - 1, cell.y + 1);
+    }
+    return active_neighbors;
+}
+
+@compute
+@workgroup_size(${WORKGROUP_SIZE},${WORKGROUP_SIZE})
+fn computeMain(@builtin(global_invocation_id) cell: vec3u) {
+
+    let cell = vec2i(cell.xy);
+
+    // Skip the invocations of partially filled workgroups that fall outside the grid.
+    if (cell.x >= i32(uniforms.grid.x) || cell.y >= i32(uniforms.grid.y)) {
+        return;
+    }
+
+    let i = cell_index(cell);
+
+    // The frame of a bordered grid stays dead, so the interior never reaches the edge.
+    let grid = vec2i(uniforms.grid);
+    if (uniforms.boundary == BOUNDARY_BORDER &&
+        (cell.x == 0 || cell.y == 0 || cell.x == grid.x - 1 || cell.y == grid.y - 1)) {
+        cell_state_out[i] = 0u;
+        cell_age_out[i] = 0u;
+        cell_trail_out[i] = cell_trail_in[i] * uniforms.trail_decay;
+        return;
+    }
+
+    let active_neighbors = count_neighbors(cell);
+
+    // Dead cells use the birth rule, live cells the survival rule. Live cells
+    // that don't survive and dying cells move on to the next dying state, which
+    // wraps around to dead.
+    let state = cell_state_in[i];
+    let alive = state == 1u;
+    let rule = select(uniforms.birth, uniforms.survive, alive);
+    var next = 0u;
+    if (state <= 1u) {
+        next = (rule >> active_neighbors) & 1u;
+    }
+    if (state != 0u && next == 0u) {
+        next = (state + 1u) % uniforms.states;
+    }
+    // Sources and sinks of constructed circuits.
+    let fixed = fixed_cells[i];
+    if (fixed != 0u) {
+        next = u32(fixed == FIXED_ALIVE);
+    }
+    cell_state_out[i] = next;
+
+    // Only cells that stay alive grow older, newborn and dead cells start from 0.
+    cell_age_out[i] = select(0u, cell_age_in[i] + 1u, alive && next == 1u);
+
+    cell_trail_out[i] = select(cell_trail_in[i] * uniforms.trail_decay, 1.0, next == 1u);
+}
+
+// Write the neighbor counts of the input generation for the heatmap, with the same
+// counting as `computeMain` but without advancing the grid.
+@compute
+@workgroup_size(${WORKGROUP_SIZE},${WORKGROUP_SIZE})
+fn countMain(@builtin(global_invocation_id) cell: vec3u) {
+    let cell = vec2i(cell.xy);
+    if (cell.x >= i32(uniforms.grid.x) || cell.y >= i32(uniforms.grid.y)) {
+        return;
+    }
+    neighbor_count[cell_index(cell)] = count_neighbors(cell);
+}
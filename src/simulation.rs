@@ -0,0 +1,1771 @@
+use std::{sync::Arc, time::Duration};
+
+use rand::{distributions::Bernoulli, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    gpu::{byte_length, read_buffer, GpuTimer, PendingRead},
+    pattern::Pattern,
+    reference::step_cpu,
+    renderer::{Palette, Shape},
+    rule::Rule,
+};
+
+/// Values of fixed cells, matching `FIXED_DEAD` and `FIXED_ALIVE` in the simulation shader.
+const FIXED_DEAD: u32 = 1;
+const FIXED_ALIVE: u32 = 2;
+
+/// Most buffers in the ring that the generations are written to, see [`Settings::state_buffers`].
+pub const MAX_STATE_BUFFERS: usize = 16;
+
+/// How neighbors beyond the edge of the grid are treated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// The grid wraps around into a torus.
+    #[default]
+    Wrap,
+    /// Cells beyond the edge are always dead.
+    Dead,
+    /// The cells on the edge are always dead too, so only the interior of `width - 2` by
+    /// `height - 2` cells lives. Unlike with `Dead`, nothing lives right on the edge.
+    Border,
+}
+
+impl Boundary {
+    /// Whether the cell at `x`, `y` of a `width` by `height` grid is in the always dead
+    /// frame of [`Boundary::Border`].
+    pub fn is_frame(self, x: isize, y: isize, width: isize, height: isize) -> bool {
+        self == Boundary::Border && (x == 0 || y == 0 || x == width - 1 || y == height - 1)
+    }
+}
+
+impl std::str::FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(Boundary::Wrap),
+            "dead" => Ok(Boundary::Dead),
+            "border" => Ok(Boundary::Border),
+            _ => Err("expected 'wrap', 'dead' or 'border'".to_string()),
+        }
+    }
+}
+
+/// What computes the next generation. Either way the cells are drawn from the same buffers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SimBackend {
+    /// The compute shader.
+    #[default]
+    Gpu,
+    /// The CPU reference, which reads the cells back and uploads the result. Against a
+    /// hardware GPU it is orders of magnitude slower, around 2e7 cells/s on one core, which
+    /// is about what a software adapter like llvmpipe manages with the compute shader.
+    /// Cell ages, trails and the neighbor heatmap are left out.
+    Cpu,
+}
+
+impl std::str::FromStr for SimBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gpu" => Ok(SimBackend::Gpu),
+            "cpu" => Ok(SimBackend::Cpu),
+            _ => Err("expected 'gpu' or 'cpu'".to_string()),
+        }
+    }
+}
+
+/// How a random grid is mirrored, for symmetric soups.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// The right half mirrors the left half.
+    Mirror,
+    /// The right half mirrors the left half and the second half of the rows the first.
+    Quad,
+}
+
+impl std::str::FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Symmetry::None),
+            "mirror" => Ok(Symmetry::Mirror),
+            "quad" => Ok(Symmetry::Quad),
+            _ => Err("expected 'none', 'mirror' or 'quad'".to_string()),
+        }
+    }
+}
+
+impl Symmetry {
+    /// The cell whose random state the cell at `col`, `row` takes. The middle column and row
+    /// of an odd grid are their own mirror image. Matches `source` in the seed shader.
+    fn source(self, col: usize, row: usize, grid_size: GridSize) -> (usize, usize) {
+        let mirror = |i: usize, side: usize| i.min(side - 1 - i);
+        match self {
+            Symmetry::None => (col, row),
+            Symmetry::Mirror => (mirror(col, grid_size.width), row),
+            Symmetry::Quad => (mirror(col, grid_size.width), mirror(row, grid_size.height)),
+        }
+    }
+}
+
+/// Which cells around a cell count as its neighbors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The 8 surrounding cells.
+    #[default]
+    Moore,
+    /// Only the 4 orthogonally adjacent cells, so rules can count up to 4 neighbors.
+    VonNeumann,
+}
+
+impl std::str::FromStr for Neighborhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moore" => Ok(Neighborhood::Moore),
+            "vonneumann" => Ok(Neighborhood::VonNeumann),
+            _ => Err("expected 'moore' or 'vonneumann'".to_string()),
+        }
+    }
+}
+
+/// The number of columns and rows of cells, given as `WxH` or as `N` for a square grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GridSize {
+    pub fn square(size: usize) -> GridSize {
+        GridSize {
+            width: size,
+            height: size,
+        }
+    }
+
+    /// Number of cells in the grid.
+    pub fn cells(self) -> usize {
+        self.width * self.height
+    }
+
+    /// The grid with both sides multiplied by `factor`, or divided when it is below 1,
+    /// keeping at least one cell on each side.
+    pub fn scaled(self, factor: f64) -> GridSize {
+        let scale = |side: usize| ((side as f64 * factor) as usize).max(1);
+        GridSize {
+            width: scale(self.width),
+            height: scale(self.height),
+        }
+    }
+}
+
+impl std::fmt::Display for GridSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl std::str::FromStr for GridSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let side = |side: &str| {
+            side.parse::<usize>()
+                .map_err(|_| "expected WxH like 64x16".to_string())
+        };
+        match s.split_once('x') {
+            Some((width, height)) => Ok(GridSize {
+                width: side(width)?,
+                height: side(height)?,
+            }),
+            None => side(s).map(GridSize::square),
+        }
+    }
+}
+
+/// How a [`Simulation`] is set up and how its cells are drawn, apart from its rule.
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub grid_size: GridSize,
+    pub boundary: Boundary,
+    pub neighborhood: Neighborhood,
+    pub sim_backend: SimBackend,
+    /// Probability of a cell starting out alive on a random grid.
+    pub density: f64,
+    pub symmetry: Symmetry,
+    /// Seed for the random grid, chosen at random when not given.
+    pub seed: Option<u64>,
+    /// Side of the square compute workgroups.
+    pub workgroup_size: u32,
+    /// Number of buffers the generations are written to in turn, from 2 to
+    /// [`MAX_STATE_BUFFERS`]. Those before the current one hold the previous generations.
+    pub state_buffers: usize,
+    /// Color cells by how long they have been alive instead of by position.
+    pub color_by_age: bool,
+    /// Fraction of each cell left empty as a border.
+    pub cell_gap: f32,
+    /// Draw dead cells too, so the gaps show up as lines between all cells.
+    pub grid_lines: bool,
+    /// Draw dead cells as faint ghosts of their live color, so the grid always shows.
+    pub show_dead: bool,
+    pub shape: Shape,
+    /// Fade cells in and out between generations.
+    pub smooth: bool,
+    pub palette: Palette,
+    /// Highlight live cells on the edges of a wrapping grid.
+    pub show_wrap: bool,
+    /// Factor the afterimage of dead cells fades by each generation, 1 for no trail.
+    pub trail: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            grid_size: GridSize::square(32),
+            boundary: Boundary::default(),
+            neighborhood: Neighborhood::default(),
+            sim_backend: SimBackend::default(),
+            density: 0.6,
+            symmetry: Symmetry::default(),
+            seed: None,
+            workgroup_size: 8,
+            state_buffers: 2,
+            color_by_age: false,
+            cell_gap: 0.2,
+            grid_lines: false,
+            show_dead: false,
+            shape: Shape::default(),
+            smooth: false,
+            palette: Palette::default(),
+            show_wrap: false,
+            trail: 1.0,
+        }
+    }
+}
+
+/// Matches the `Uniforms` struct in the shaders.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    grid: [f32; 2],
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    color_by_age: u32,
+    cell_gap: f32,
+    grid_lines: u32,
+    view: [f32; 4],
+    shape: u32,
+    interpolate: u32,
+    progress: f32,
+    custom_color: u32,
+    alive_color: [f32; 4],
+    palette: u32,
+    neighborhood: u32,
+    states: u32,
+    show_wrap: u32,
+    trail_decay: f32,
+    gradient: u32,
+    heatmap: u32,
+    show_dead: u32,
+    invert: u32,
+    high_contrast: u32,
+    _padding: [u32; 2],
+}
+
+/// Fill `buffer` and its CPU copy `cells` with random cells of the given density,
+/// mirrored by `symmetry`.
+///
+/// Large grids are seeded on the GPU and leave `cells` as is.
+fn seed_cells(
+    seeder: &GpuSeeder,
+    buffer: &wgpu::Buffer,
+    cells: &mut [u32],
+    grid_size: GridSize,
+    rng: &mut StdRng,
+    density: f64,
+    symmetry: Symmetry,
+) {
+    if cells.len() >= GPU_SEED_MIN_CELLS {
+        seeder.seed(buffer, grid_size, rng.gen(), density, symmetry);
+        return;
+    }
+
+    let dist = Bernoulli::new(density).unwrap();
+    for cell in cells.iter_mut() {
+        *cell = dist.sample(rng) as u32;
+    }
+    // A mirror image comes after the cell it mirrors, which is its own source.
+    if symmetry != Symmetry::None {
+        for row in 0..grid_size.height {
+            for col in 0..grid_size.width {
+                let (source_col, source_row) = symmetry.source(col, row, grid_size);
+                cells[row * grid_size.width + col] =
+                    cells[source_row * grid_size.width + source_col];
+            }
+        }
+    }
+    seeder
+        .queue
+        .write_buffer(buffer, 0, bytemuck::cast_slice(cells));
+}
+
+/// Record clearing the cells on the edge of the grid in `buffer`. The last cell of a row
+/// and the first of the next are next to each other, so they are cleared together.
+fn clear_frame(encoder: &mut wgpu::CommandEncoder, buffer: &wgpu::Buffer, grid_size: GridSize) {
+    let cell = std::mem::size_of::<u32>() as u64;
+    let (width, height) = (grid_size.width as u64, grid_size.height as u64);
+    let row = width * cell;
+    encoder.clear_buffer(buffer, 0, wgpu::BufferSize::new(row));
+    encoder.clear_buffer(buffer, (height - 1) * row, wgpu::BufferSize::new(row));
+    for y in 1..height {
+        encoder.clear_buffer(buffer, y * row - cell, wgpu::BufferSize::new(2 * cell));
+    }
+}
+
+fn simulation_shader_source(source: &str, workgroup_size: u32) -> String {
+    source.replace("${WORKGROUP_SIZE}", &format!("{workgroup_size}"))
+}
+
+/// Grids with at least this many cells are seeded on the GPU, smaller ones on the CPU.
+const GPU_SEED_MIN_CELLS: usize = 256 * 256;
+
+/// Matches the `SeedParams` struct in the seed shader.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SeedParams {
+    grid: [u32; 2],
+    seed: u32,
+    density: f32,
+    symmetry: u32,
+    _padding: u32,
+}
+
+/// The compute pipeline that fills a cell buffer with random cells of a given density,
+/// instead of generating and uploading them on the CPU. It is built once, so re-seeding
+/// a large grid takes a single dispatch.
+struct GpuSeeder {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pipeline: wgpu::ComputePipeline,
+    params_buffer: wgpu::Buffer,
+}
+
+impl GpuSeeder {
+    fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> GpuSeeder {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Seed shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "shaders/seed.wgsl"
+            ))),
+        });
+        // Let wgpu derive the bind group layout from the shader.
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Seed pipeline"),
+            layout: None,
+            module: &module,
+            entry_point: "seedMain",
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Seed params"),
+            size: std::mem::size_of::<SeedParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+        GpuSeeder {
+            device,
+            queue,
+            pipeline,
+            params_buffer,
+        }
+    }
+
+    /// Fill `buffer` with random cells. A different `seed` gives different cells.
+    fn seed(
+        &self,
+        buffer: &wgpu::Buffer,
+        grid_size: GridSize,
+        seed: u32,
+        density: f64,
+        symmetry: Symmetry,
+    ) {
+        let (device, queue) = (&self.device, &self.queue);
+        let params = SeedParams {
+            grid: [grid_size.width as u32, grid_size.height as u32],
+            seed,
+            density: density as f32,
+            symmetry: symmetry as u32,
+            _padding: 0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        // The cell buffers are replaced when the grid is resized, so the bind group isn't kept.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Seed bind group"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        // Matches `@workgroup_size(8, 8)` in the shader.
+        compute_pass.dispatch_workgroups(
+            (grid_size.width as u32).div_ceil(8),
+            (grid_size.height as u32).div_ceil(8),
+            1,
+        );
+        drop(compute_pass);
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// Check that the cell buffers of a grid fit the device limits.
+fn check_grid_size(limits: &wgpu::Limits, grid_size: GridSize) -> Result<(), String> {
+    let cell_size = std::mem::size_of::<u32>() as u64;
+    let bytes = grid_size.cells() as u64 * cell_size;
+    for (limit, name) in [
+        (
+            limits.max_storage_buffer_binding_size as u64,
+            "storage buffer binding size",
+        ),
+        (limits.max_buffer_size, "buffer size"),
+    ] {
+        if bytes > limit {
+            let max_cells = limit / cell_size;
+            let max_grid_size = (max_cells as f64).sqrt() as u64;
+            return Err(format!(
+                "Grid size {grid_size} needs {bytes} byte buffers, over the maximum {name} of {limit} bytes. Grids of up to {max_cells} cells fit, like {max_grid_size}x{max_grid_size}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check that a `workgroup_size` x `workgroup_size` workgroup fits the device limits.
+fn check_workgroup_size(limits: &wgpu::Limits, workgroup_size: u32) -> Result<(), String> {
+    let max_size = limits
+        .max_compute_workgroup_size_x
+        .min(limits.max_compute_workgroup_size_y);
+    if workgroup_size > max_size {
+        return Err(format!(
+            "Workgroup size {workgroup_size} exceeds the device limit of {max_size}"
+        ));
+    }
+    let max_invocations = limits.max_compute_invocations_per_workgroup;
+    if workgroup_size * workgroup_size > max_invocations {
+        return Err(format!(
+            "Workgroup size {workgroup_size} needs {} invocations, but the device limit is {max_invocations}",
+            workgroup_size * workgroup_size
+        ));
+    }
+    Ok(())
+}
+
+/// Check that a simulation with `settings` fits the device limits, before creating it.
+pub fn check_limits(limits: &wgpu::Limits, settings: &Settings) -> Result<(), String> {
+    check_grid_size(limits, settings.grid_size)?;
+    check_workgroup_size(limits, settings.workgroup_size)
+}
+
+/// The pipelines that advance the cells and that count their neighbors for the heatmap,
+/// from the same shader.
+fn create_simulation_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+) -> (wgpu::ComputePipeline, wgpu::ComputePipeline) {
+    let pipeline = |label, entry_point| {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            module,
+            entry_point,
+        })
+    };
+    (
+        pipeline("Simulation pipeline", "computeMain"),
+        pipeline("Neighbor count pipeline", "countMain"),
+    )
+}
+
+/// Create `count` cell state, age and trail buffers of a grid, and the staging buffer to
+/// read the cells back.
+fn create_cell_buffers(
+    device: &wgpu::Device,
+    grid_size: GridSize,
+    count: usize,
+) -> (
+    Vec<wgpu::Buffer>,
+    Vec<wgpu::Buffer>,
+    Vec<wgpu::Buffer>,
+    wgpu::Buffer,
+) {
+    let size = (grid_size.cells() * std::mem::size_of::<u32>()) as u64;
+    let buffers = |name: &str, usage| {
+        (0..count)
+            .map(|i| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("{name} {}", (b'A' + i as u8) as char)),
+                    size,
+                    usage,
+                    mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+                })
+            })
+            .collect()
+    };
+
+    // Create the storage buffers to hold the cell state.
+    let cell_state_storage = buffers(
+        "Cell State",
+        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+    );
+
+    // And as many for the number of generations each cell has been alive,
+    // which start out zeroed.
+    let cell_age_storage = buffers(
+        "Cell Age",
+        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    );
+
+    // And for the fading trail of cells that died, from 1 while alive down to 0.
+    let cell_trail_storage = buffers(
+        "Cell Trail",
+        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    );
+
+    // Staging buffer to read back the whole grid.
+    let state_readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cell state readback"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+    });
+
+    (
+        cell_state_storage,
+        cell_age_storage,
+        cell_trail_storage,
+        state_readback_buffer,
+    )
+}
+
+/// The buffer in a ring of `count` that the generation before the one in `step` is in.
+fn previous(step: usize, count: usize) -> usize {
+    (step + count - 1) % count
+}
+
+/// Bind groups for the compute pipeline, one for each buffer, reading the cells of that
+/// buffer and writing the next generation to the next one in the ring.
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    cell_state_storage: &[wgpu::Buffer],
+    cell_age_storage: &[wgpu::Buffer],
+    cell_trail_storage: &[wgpu::Buffer],
+    per_cell: &[&wgpu::Buffer],
+) -> Vec<wgpu::BindGroup> {
+    // Bindings 7 and 8, the neighbor counts and the fixed cells, aren't swapped.
+    let count = cell_state_storage.len();
+    (0..count)
+        .map(|step| {
+            let next = (step + 1) % count;
+            let mut buffers = vec![
+                uniform_buffer,
+                &cell_state_storage[step],
+                &cell_state_storage[next],
+                &cell_age_storage[step],
+                &cell_age_storage[next],
+                &cell_trail_storage[step],
+                &cell_trail_storage[next],
+            ];
+            buffers.extend(per_cell);
+            buffer_bind_group(device, "Cell bind group", layout, &buffers)
+        })
+        .collect()
+}
+
+/// Bind groups for the cell render pipeline, one for the cells in each buffer. All
+/// also bind the `per_cell` buffers that aren't swapped between generations.
+fn create_render_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    cell_state_storage: &[wgpu::Buffer],
+    cell_age_storage: &[wgpu::Buffer],
+    cell_trail_storage: &[wgpu::Buffer],
+    per_cell: &[&wgpu::Buffer],
+) -> Vec<wgpu::BindGroup> {
+    // Binding 1 is the current generation, 2 the previous one, 3 the cell ages, 4 the trail,
+    // 5 the cells to draw and 6 the neighbor counts.
+    let count = cell_state_storage.len();
+    (0..count)
+        .map(|step| {
+            let mut buffers = vec![
+                uniform_buffer,
+                &cell_state_storage[step],
+                &cell_state_storage[previous(step, count)],
+                &cell_age_storage[step],
+                &cell_trail_storage[step],
+            ];
+            buffers.extend(per_cell);
+            buffer_bind_group(device, "Cell render bind group", layout, &buffers)
+        })
+        .collect()
+}
+
+/// A buffer of one `u32` per cell that isn't swapped between generations, like the
+/// cells the cull shader lists to draw, the neighbor counts or the fixed cells.
+fn create_per_cell_buffer(device: &wgpu::Device, label: &str, grid_size: GridSize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: (grid_size.cells() * std::mem::size_of::<u32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+    })
+}
+
+/// Bind groups for the cull pipeline, one for the cells in each buffer.
+fn create_cull_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    cell_state_storage: &[wgpu::Buffer],
+    cell_trail_storage: &[wgpu::Buffer],
+    visible_cells: &wgpu::Buffer,
+    draw_args: &wgpu::Buffer,
+) -> Vec<wgpu::BindGroup> {
+    let count = cell_state_storage.len();
+    (0..count)
+        .map(|step| {
+            let buffers = [
+                uniform_buffer,
+                &cell_state_storage[step],
+                &cell_state_storage[previous(step, count)],
+                &cell_trail_storage[step],
+                visible_cells,
+                draw_args,
+            ];
+            buffer_bind_group(device, "Cull bind group", layout, &buffers)
+        })
+        .collect()
+}
+
+/// A bind group with each of `buffers` bound in order, starting from binding 0.
+fn buffer_bind_group(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    buffers: &[&wgpu::Buffer],
+) -> wgpu::BindGroup {
+    let entries: Vec<_> = buffers
+        .iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+        })
+        .collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &entries,
+    })
+}
+
+/// Called with the number of each generation, and its cells if they were asked for.
+type Hook = dyn FnMut(u64, Option<&[u32]>);
+
+/// A closure registered with [`Simulation::on_generation`].
+struct GenerationHook {
+    read_cells: bool,
+    hook: Box<Hook>,
+}
+
+/// The cell state buffers and the compute pipeline that advances them, independent of any window.
+pub struct Simulation {
+    // Shared with a second simulation when comparing rules.
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    grid_size: GridSize,
+    rule: Rule,
+    boundary: Boundary,
+    neighborhood: Neighborhood,
+    sim_backend: SimBackend,
+    symmetry: Symmetry,
+    workgroup_size: u32,
+    uniform_buffer: wgpu::Buffer,
+    cell_state_array: Vec<u32>,
+    // A ring of buffers that the generations are written to in turn, `step` is the current one.
+    cell_state_storage: Vec<wgpu::Buffer>,
+    cell_age_storage: Vec<wgpu::Buffer>,
+    cell_trail_storage: Vec<wgpu::Buffer>,
+    state_readback_buffer: wgpu::Buffer,
+    #[cfg(feature = "hot-reload")]
+    pipeline_layout: wgpu::PipelineLayout,
+    // Kept to create new bind groups when the grid is resized.
+    bind_group_layout: wgpu::BindGroupLayout,
+    render_bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Vec<wgpu::BindGroup>,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    render_bind_group: Vec<wgpu::BindGroup>,
+    simulation_pipeline: wgpu::ComputePipeline,
+    // Writes the live neighbors of each cell to `neighbor_count` for the heatmap, without
+    // changing the cells.
+    count_pipeline: wgpu::ComputePipeline,
+    neighbor_count: wgpu::Buffer,
+    // Cells held dead or alive whatever the rule, see `Fixed`, with a copy on the CPU to
+    // upload single cells from and to apply to the CPU backend.
+    fixed_cells: wgpu::Buffer,
+    fixed_array: Vec<u32>,
+    heatmap: bool,
+    // Lists the cells that are drawn, so the render pass skips the dead ones.
+    cull_pipeline: wgpu::ComputePipeline,
+    cull_bind_group: Vec<wgpu::BindGroup>,
+    visible_cells: wgpu::Buffer,
+    draw_args: wgpu::Buffer,
+    rng: StdRng,
+    seeder: GpuSeeder,
+    timer: Option<GpuTimer>,
+    hooks: Vec<GenerationHook>,
+    step: usize,
+    generation: u64,
+}
+
+impl Simulation {
+    /// Create the simulation of `rule`, starting from `initial_cells` when given or from a
+    /// random grid.
+    pub fn new(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        settings: &Settings,
+        rule: Rule,
+        initial_cells: Option<Vec<u32>>,
+    ) -> anyhow::Result<Simulation> {
+        let grid_size = settings.grid_size;
+        let workgroup_size = settings.workgroup_size;
+        check_limits(&device.limits(), settings).map_err(anyhow::Error::msg)?;
+        anyhow::ensure!(
+            (2..=MAX_STATE_BUFFERS).contains(&settings.state_buffers),
+            "State buffers must be between 2 and {MAX_STATE_BUFFERS}, got {}",
+            settings.state_buffers
+        );
+
+        let uniforms = Uniforms {
+            grid: [grid_size.width as f32, grid_size.height as f32],
+            birth: rule.birth,
+            survive: rule.survive,
+            boundary: settings.boundary as u32,
+            color_by_age: settings.color_by_age as u32,
+            cell_gap: settings.cell_gap,
+            grid_lines: settings.grid_lines as u32,
+            view: [1.0, 1.0, 0.0, 0.0],
+            shape: settings.shape as u32,
+            interpolate: settings.smooth as u32,
+            progress: 0.0,
+            custom_color: 0,
+            alive_color: [1.0; 4],
+            palette: settings.palette as u32,
+            neighborhood: settings.neighborhood as u32,
+            states: rule.states,
+            show_wrap: settings.show_wrap as u32,
+            trail_decay: settings.trail,
+            gradient: 0,
+            heatmap: 0,
+            show_dead: settings.show_dead as u32,
+            invert: 0,
+            high_contrast: 0,
+            _padding: [0; 2],
+        };
+        if settings.neighborhood == Neighborhood::VonNeumann
+            && (rule.birth | rule.survive) >> 5 != 0
+        {
+            log::warn!("Cells have at most 4 von Neumann neighbors, counts above 4 in the rule never apply");
+        }
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Grid Uniforms"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        queue.write_buffer(&uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        // Create an array representing the active state of each cell.
+        let mut cell_state_array = vec![0u32; grid_size.cells()];
+
+        let (cell_state_storage, cell_age_storage, cell_trail_storage, state_readback_buffer) =
+            create_cell_buffers(&device, grid_size, settings.state_buffers);
+
+        // Set each cell to a random state, then copy the array into the storage buffer.
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let seeder = GpuSeeder::new(device.clone(), queue.clone());
+        match initial_cells {
+            Some(cells) => {
+                assert_eq!(
+                    cells.len(),
+                    cell_state_array.len(),
+                    "Initial cells don't fit the grid"
+                );
+                cell_state_array = cells;
+                queue.write_buffer(
+                    &cell_state_storage[0],
+                    0,
+                    bytemuck::cast_slice(&cell_state_array),
+                );
+            }
+            None => {
+                seed_cells(
+                    &seeder,
+                    &cell_state_storage[0],
+                    &mut cell_state_array,
+                    grid_size,
+                    &mut rng,
+                    settings.density,
+                    settings.symmetry,
+                );
+            }
+        }
+        if settings.boundary == Boundary::Border {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            clear_frame(&mut encoder, &cell_state_storage[0], grid_size);
+            queue.submit(Some(encoder.finish()));
+        }
+
+        // Create the compute shader that will process the simulation.
+        let simulation_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Game of Life simulation shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(simulation_shader_source(
+                include_str!("shaders/simulation.wgsl"),
+                workgroup_size,
+            ))),
+        });
+
+        // Create the bind group layout and pipeline layout.
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Cell Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX
+                        | wgpu::ShaderStages::COMPUTE
+                        | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cell Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let neighbor_count = create_per_cell_buffer(&device, "Neighbor count", grid_size);
+        let fixed_cells = create_per_cell_buffer(&device, "Fixed cells", grid_size);
+        let bind_group = create_bind_groups(
+            &device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &cell_state_storage,
+            &cell_age_storage,
+            &cell_trail_storage,
+            &[&neighbor_count, &fixed_cells],
+        );
+
+        // The cell render pipeline only reads the cells, so it gets its own layout that
+        // also binds the previous generation read-only, to interpolate between the two. The
+        // minimap reads the cells in its fragment shader.
+        let render_storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: if binding == 1 {
+                wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT
+            } else {
+                wgpu::ShaderStages::VERTEX
+            },
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cell Render Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    render_storage_entry(1),
+                    render_storage_entry(2),
+                    render_storage_entry(3),
+                    render_storage_entry(4),
+                    render_storage_entry(5),
+                    render_storage_entry(6),
+                ],
+            });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Cell Render Pipeline Layout"),
+                bind_group_layouts: &[&render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let visible_cells = create_per_cell_buffer(&device, "Visible cells", grid_size);
+        let render_bind_group = create_render_bind_groups(
+            &device,
+            &render_bind_group_layout,
+            &uniform_buffer,
+            &cell_state_storage,
+            &cell_age_storage,
+            &cell_trail_storage,
+            &[&visible_cells, &neighbor_count],
+        );
+
+        // Create a compute pipeline that updates the game state, and one that counts the
+        // neighbors for the heatmap.
+        let (simulation_pipeline, count_pipeline) =
+            create_simulation_pipelines(&device, &pipeline_layout, &simulation_shader_module);
+
+        // And one that lists the cells to draw, with its bind group layout derived from the shader.
+        let cull_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cull shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "shaders/cull.wgsl"
+            ))),
+        });
+        let cull_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull pipeline"),
+            layout: None,
+            module: &cull_module,
+            entry_point: "cullMain",
+        });
+        let draw_args = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell draw arguments"),
+            size: std::mem::size_of::<wgpu::util::DrawIndexedIndirect>() as u64,
+            usage: wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+        let cull_bind_group = create_cull_bind_groups(
+            &device,
+            &cull_pipeline.get_bind_group_layout(0),
+            &uniform_buffer,
+            &cell_state_storage,
+            &cell_trail_storage,
+            &visible_cells,
+            &draw_args,
+        );
+
+        let timer = GpuTimer::new(&device, &queue);
+
+        Ok(Simulation {
+            device,
+            queue,
+            grid_size,
+            workgroup_size,
+            uniform_buffer,
+            cell_state_array,
+            cell_state_storage,
+            cell_age_storage,
+            cell_trail_storage,
+            rule,
+            boundary: settings.boundary,
+            neighborhood: settings.neighborhood,
+            sim_backend: settings.sim_backend,
+            symmetry: settings.symmetry,
+            state_readback_buffer,
+            #[cfg(feature = "hot-reload")]
+            pipeline_layout,
+            bind_group_layout,
+            render_bind_group_layout,
+            bind_group,
+            render_pipeline_layout,
+            render_bind_group,
+            simulation_pipeline,
+            count_pipeline,
+            neighbor_count,
+            fixed_cells,
+            fixed_array: vec![0; grid_size.cells()],
+            heatmap: false,
+            cull_pipeline,
+            cull_bind_group,
+            visible_cells,
+            draw_args,
+            rng,
+            seeder,
+            timer,
+            hooks: Vec::new(),
+            step: 0,
+            generation: 0,
+        })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn grid_size(&self) -> GridSize {
+        self.grid_size
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    /// Number of generations since the grid was last seeded.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// GPU time of the last measured compute pass, once it has been read back, or `None`
+    /// without timestamp queries and until the next measurement after being taken.
+    pub fn take_gpu_time(&mut self) -> Option<Duration> {
+        self.timer.as_mut().and_then(GpuTimer::take_latest)
+    }
+
+    /// Storage buffer holding the current generation, to read it back with
+    /// [`read_cell_state`](crate::gpu::read_cell_state).
+    pub fn cell_state_storage(&self) -> &wgpu::Buffer {
+        &self.cell_state_storage[self.step]
+    }
+
+    /// Layout for the cell render pipeline.
+    pub fn render_pipeline_layout(&self) -> &wgpu::PipelineLayout {
+        &self.render_pipeline_layout
+    }
+
+    /// Layout of [`Simulation::render_bind_group`], for other pipelines that draw the cells.
+    pub fn render_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.render_bind_group_layout
+    }
+
+    /// Bind group with the current and previous generation for drawing.
+    pub fn render_bind_group(&self) -> &wgpu::BindGroup {
+        &self.render_bind_group[self.step]
+    }
+
+    /// Arguments for `draw_indexed_indirect` that draw the cells listed by [`Simulation::cull`].
+    pub fn draw_args(&self) -> &wgpu::Buffer {
+        &self.draw_args
+    }
+
+    /// Record a compute pass that lists the cells the cell shader draws anything for,
+    /// and sets the draw arguments to draw those with `index_count` indices each. With
+    /// the heatmap, the neighbor counts of the current generation are written first.
+    pub fn cull(&self, encoder: &mut wgpu::CommandEncoder, index_count: u32) {
+        if self.heatmap {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Neighbor count pass"),
+            });
+            let workgroup_count = |side: usize| (side as u32).div_ceil(self.workgroup_size);
+            pass.set_pipeline(&self.count_pipeline);
+            pass.set_bind_group(0, &self.bind_group[self.step], &[]);
+            pass.dispatch_workgroups(
+                workgroup_count(self.grid_size.width),
+                workgroup_count(self.grid_size.height),
+                1,
+            );
+        }
+
+        let args = wgpu::util::DrawIndexedIndirect {
+            vertex_count: index_count,
+            instance_count: 0,
+            base_index: 0,
+            vertex_offset: 0,
+            base_instance: 0,
+        };
+        self.queue.write_buffer(&self.draw_args, 0, args.as_bytes());
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cull pass"),
+        });
+        pass.set_pipeline(&self.cull_pipeline);
+        pass.set_bind_group(0, &self.cull_bind_group[self.step], &[]);
+        pass.dispatch_workgroups(
+            (self.grid_size.width as u32).div_ceil(8),
+            (self.grid_size.height as u32).div_ceil(8),
+            1,
+        );
+    }
+
+    /// Set how far along the way to the next generation the display is, from 0 to 1.
+    pub fn set_progress(&self, progress: f32) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, progress) as u64,
+            bytemuck::bytes_of(&progress),
+        );
+    }
+
+    /// Draw live cells in a single color instead of by position.
+    pub fn set_alive_color(&self, color: Option<[f32; 3]>) {
+        let (custom_color, [r, g, b]) = match color {
+            Some(color) => (1u32, color),
+            None => (0, [0.0; 3]),
+        };
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, custom_color) as u64,
+            bytemuck::bytes_of(&custom_color),
+        );
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, alive_color) as u64,
+            bytemuck::cast_slice(&[r, g, b, 1.0]),
+        );
+    }
+
+    /// Select the colors of live cells drawn by position, see `live_color` in `cell.wgsl`.
+    pub fn set_palette(&self, palette: u32) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, palette) as u64,
+            bytemuck::bytes_of(&palette),
+        );
+    }
+
+    /// Switch to another rule from the next generation on, without starting over. Dying
+    /// cells that are beyond the last state of the new rule die right away.
+    pub fn set_rule(&mut self, rule: Rule) {
+        if rule.states < self.rule.states {
+            let mut cells = self.read_cells();
+            for cell in &mut cells {
+                if *cell >= rule.states {
+                    *cell = 0;
+                }
+            }
+            self.queue.write_buffer(
+                &self.cell_state_storage[self.step],
+                0,
+                bytemuck::cast_slice(&cells),
+            );
+        }
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, birth) as u64,
+            bytemuck::cast_slice(&[rule.birth, rule.survive]),
+        );
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, states) as u64,
+            bytemuck::bytes_of(&rule.states),
+        );
+        self.rule = rule;
+    }
+
+    /// Select how the position colors run across the grid, see `gradient_position` in `cell.wgsl`.
+    pub fn set_gradient(&self, gradient: u32) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, gradient) as u64,
+            bytemuck::bytes_of(&gradient),
+        );
+    }
+
+    /// Whether cells are colored by their number of live neighbors.
+    pub fn heatmap(&self) -> bool {
+        self.heatmap
+    }
+
+    /// Color cells by their number of live neighbors instead of by their state. This only
+    /// changes how the cells are drawn, the simulation runs the same.
+    pub fn set_heatmap(&mut self, heatmap: bool) {
+        self.heatmap = heatmap;
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, heatmap) as u64,
+            bytemuck::bytes_of(&(heatmap as u32)),
+        );
+    }
+
+    /// Draw every color as its opposite. The background is cleared by whoever draws the
+    /// cells, so it is left to them.
+    pub fn set_invert(&self, invert: bool) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, invert) as u64,
+            bytemuck::bytes_of(&(invert as u32)),
+        );
+    }
+
+    /// Draw only the live cells, in white, for a black background.
+    pub fn set_high_contrast(&self, high_contrast: bool) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, high_contrast) as u64,
+            bytemuck::bytes_of(&(high_contrast as u32)),
+        );
+    }
+
+    /// Set the scale and offset the cell shader applies to the grid.
+    pub fn set_view(&self, view: [f32; 4]) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, view) as u64,
+            bytemuck::cast_slice(&view),
+        );
+    }
+
+    /// Advance the simulation by one generation.
+    pub fn update(&mut self) {
+        self.advance(1);
+    }
+
+    /// Call `hook` after each generation with its number, and with its cells when
+    /// `read_cells` is set. Reading the cells back waits for every generation to finish on
+    /// the GPU, which is then submitted on its own, and only works where buffers can be
+    /// mapped right away, so not in the browser.
+    pub fn on_generation(
+        &mut self,
+        read_cells: bool,
+        hook: impl FnMut(u64, Option<&[u32]>) + 'static,
+    ) {
+        self.hooks.push(GenerationHook {
+            read_cells,
+            hook: Box::new(hook),
+        });
+    }
+
+    /// Advance the simulation by `generations` generations, recorded into a single compute
+    /// pass unless a hook reads back the cells of each generation.
+    pub fn advance(&mut self, generations: u32) {
+        let read_cells = self.hooks.iter().any(|hook| hook.read_cells);
+        if !read_cells {
+            self.dispatch(generations);
+            self.call_hooks(generations, None);
+            return;
+        }
+        for _ in 0..generations {
+            self.dispatch(1);
+            let cells = self.read_cells();
+            self.call_hooks(1, Some(&cells));
+        }
+    }
+
+    /// Call the hooks for the last `generations` generations, of which `cells` is the last.
+    fn call_hooks(&mut self, generations: u32, cells: Option<&[u32]>) {
+        let first = self.generation - generations as u64 + 1;
+        for generation in first..=self.generation {
+            for GenerationHook { read_cells, hook } in &mut self.hooks {
+                hook(generation, cells.filter(|_| *read_cells));
+            }
+        }
+    }
+
+    /// Record `generations` generations into a single compute pass, or compute them on the
+    /// CPU with [`SimBackend::Cpu`].
+    fn dispatch(&mut self, generations: u32) {
+        if self.sim_backend == SimBackend::Cpu {
+            self.advance_cpu(generations);
+            return;
+        }
+
+        // ```js
+        // const encoder = device.createCommandEncoder();
+        // ```
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+        if let Some(timer) = &self.timer {
+            timer.begin(&mut encoder);
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+
+        compute_pass.set_pipeline(&self.simulation_pipeline);
+
+        // Round up so grids that aren't a multiple of the workgroup size are fully covered.
+        let workgroup_count = |side: usize| (side as u32).div_ceil(self.workgroup_size);
+        let (x, y) = (
+            workgroup_count(self.grid_size.width),
+            workgroup_count(self.grid_size.height),
+        );
+        for _ in 0..generations {
+            compute_pass.set_bind_group(0, &self.bind_group[self.step], &[]);
+            compute_pass.dispatch_workgroups(x, y, 1);
+
+            // increment step
+            self.step = (self.step + 1) % self.cell_state_storage.len();
+            self.generation += 1;
+        }
+
+        drop(compute_pass);
+
+        if let Some(timer) = &mut self.timer {
+            timer.end(&self.device, &mut encoder);
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+
+        if let Some(timer) = &mut self.timer {
+            timer.poll(&self.device);
+        }
+    }
+
+    /// Advance the simulation with the CPU reference and upload the last generation into
+    /// the buffer the next compute pass would have written.
+    fn advance_cpu(&mut self, generations: u32) {
+        let (width, height) = (self.grid_size.width, self.grid_size.height);
+        let mut cells = self.read_cells();
+        for _ in 0..generations {
+            cells = step_cpu(
+                &cells,
+                width,
+                height,
+                self.rule,
+                self.boundary,
+                self.neighborhood,
+            );
+            for (cell, &fixed) in cells.iter_mut().zip(&self.fixed_array) {
+                if fixed != 0 {
+                    *cell = (fixed == FIXED_ALIVE) as u32;
+                }
+            }
+        }
+        self.step = (self.step + 1) % self.cell_state_storage.len();
+        self.generation += generations as u64;
+        self.queue.write_buffer(
+            &self.cell_state_storage[self.step],
+            0,
+            bytemuck::cast_slice(&cells),
+        );
+    }
+
+    /// Run a step of a new grid and throw it away, so the driver has compiled the compute
+    /// pipeline by the time the first generation is due.
+    pub fn warm_up(&mut self) {
+        if self.sim_backend == SimBackend::Cpu {
+            return;
+        }
+        // The grid is still in the first buffer, which `restart` keeps.
+        debug_assert_eq!(self.step, 0, "Only a new grid can be warmed up");
+        let generation = self.generation;
+        self.dispatch(1);
+        self.restart();
+        self.generation = generation;
+    }
+
+    /// Re-seed the grid with a fresh random state of the given density, as symmetric as at
+    /// startup.
+    pub fn reseed(&mut self, density: f64) {
+        seed_cells(
+            &self.seeder,
+            &self.cell_state_storage[0],
+            &mut self.cell_state_array,
+            self.grid_size,
+            &mut self.rng,
+            density,
+            self.symmetry,
+        );
+        self.restart();
+    }
+
+    /// Re-seed the grid as a simulation started with `seed` would be seeded, so the grid can
+    /// be replayed with `--seed`.
+    pub fn reseed_from(&mut self, seed: u64, density: f64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.reseed(density);
+    }
+
+    /// Make every cell live or dead.
+    pub fn fill(&mut self, alive: bool) {
+        let cells = vec![alive as u32; self.cell_state_array.len()];
+        self.load_cells(&cells);
+    }
+
+    /// Replace the grid with `cells`, stored row by row.
+    pub fn load_cells(&mut self, cells: &[u32]) {
+        self.cell_state_array.copy_from_slice(cells);
+        self.queue.write_buffer(
+            &self.cell_state_storage[0],
+            0,
+            bytemuck::cast_slice(&self.cell_state_array),
+        );
+        self.restart();
+    }
+
+    /// Go back to an earlier `generation` with its `cells`. Cell ages aren't
+    /// kept, so they start over.
+    pub fn restore(&mut self, generation: u64, cells: &[u32]) {
+        self.load_cells(cells);
+        self.generation = generation;
+    }
+
+    /// Start again from generation 0 in `cell_state_storage[0]`, with all ages and trails reset
+    /// and the frame of a bordered grid cleared.
+    fn restart(&mut self) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        for buffer in self.cell_state_storage[1..]
+            .iter()
+            .chain(&self.cell_age_storage)
+            .chain(&self.cell_trail_storage)
+        {
+            encoder.clear_buffer(buffer, 0, None);
+        }
+        if self.boundary == Boundary::Border {
+            clear_frame(&mut encoder, &self.cell_state_storage[0], self.grid_size);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        self.step = 0;
+        self.generation = 0;
+    }
+
+    /// Make the `size` by `size` block of cells around `col`, `row` live or dead.
+    /// The block wraps around the edges of a wrapping grid and is cut off otherwise.
+    /// With `fixed`, the cells stay that way every generation whatever the rule, as the
+    /// sources and sinks of a circuit, until they are painted over without it.
+    pub fn paint(&mut self, col: usize, row: usize, size: usize, alive: bool, fixed: bool) {
+        let start = -((size as isize - 1) / 2);
+        let offsets = start..start + size as isize;
+        for dy in offsets.clone() {
+            for dx in offsets.clone() {
+                self.set_cell(col as isize + dx, row as isize + dy, alive, fixed);
+            }
+        }
+    }
+
+    /// Replace the cells under `pattern`, centered on `col`, `row`, with those of the
+    /// pattern. The pattern wraps around the edges of a wrapping grid and is cut off
+    /// otherwise.
+    pub fn stamp(&mut self, col: usize, row: usize, pattern: &Pattern) {
+        let mut live = vec![false; pattern.width * pattern.height];
+        for &(x, y) in &pattern.cells {
+            live[y * pattern.width + x] = true;
+        }
+        // The pattern's rows go down from its top row, while the grid's rows go up.
+        let left = col as isize - (pattern.width as isize - 1) / 2;
+        let top = row as isize + (pattern.height as isize - 1) / 2;
+        for (i, &alive) in live.iter().enumerate() {
+            let (x, y) = (i % pattern.width, i / pattern.width);
+            self.set_cell(left + x as isize, top - y as isize, alive, false);
+        }
+    }
+
+    /// Make the cell at `x`, `y` live or dead, and fixed that way or not, wrapping around
+    /// the edges of a wrapping grid and skipping cells off a dead-edged one.
+    fn set_cell(&mut self, x: isize, y: isize, alive: bool, fixed: bool) {
+        let (width, height) = (
+            self.grid_size.width as isize,
+            self.grid_size.height as isize,
+        );
+        let (x, y) = match self.boundary {
+            Boundary::Wrap => (x.rem_euclid(width), y.rem_euclid(height)),
+            _ if !(0..width).contains(&x) || !(0..height).contains(&y) => return,
+            _ if self.boundary.is_frame(x, y, width, height) => return,
+            _ => (x, y),
+        };
+        let index = (y * width + x) as usize;
+        self.cell_state_array[index] = alive as u32;
+
+        // `cell_state_array` is not kept in sync with the simulation, so only
+        // the changed cells are uploaded, to the input of the next step.
+        let offset = (index * std::mem::size_of::<u32>()) as u64;
+        self.queue.write_buffer(
+            &self.cell_state_storage[self.step],
+            offset,
+            bytemuck::cast_slice(&self.cell_state_array[index..index + 1]),
+        );
+        // Either way the cell starts over without a history.
+        self.queue
+            .write_buffer(&self.cell_age_storage[self.step], offset, &[0; 4]);
+
+        let fixed = match (fixed, alive) {
+            (false, _) => 0,
+            (true, false) => FIXED_DEAD,
+            (true, true) => FIXED_ALIVE,
+        };
+        if self.fixed_array[index] != fixed {
+            self.fixed_array[index] = fixed;
+            self.queue.write_buffer(
+                &self.fixed_cells,
+                offset,
+                bytemuck::cast_slice(&self.fixed_array[index..index + 1]),
+            );
+        }
+    }
+
+    /// Change the grid to `grid_size` cells, keeping the cells in the top-left corner.
+    /// Cells are cut off when shrinking and the new ones are dead, cell ages start over and
+    /// fixed cells are released.
+    pub fn resize_grid(&mut self, grid_size: GridSize) -> Result<(), String> {
+        check_grid_size(&self.device.limits(), grid_size)?;
+
+        // Row 0 of the grid is drawn at the bottom, so the top rows are the last ones.
+        let old_size = self.grid_size;
+        let old_cells = self.read_cells();
+        let mut cells = vec![0u32; grid_size.cells()];
+        let kept = old_size.width.min(grid_size.width);
+        for y in 0..old_size.height.min(grid_size.height) {
+            let old_row = (old_size.height - 1 - y) * old_size.width;
+            let row = (grid_size.height - 1 - y) * grid_size.width;
+            cells[row..row + kept].copy_from_slice(&old_cells[old_row..old_row + kept]);
+        }
+
+        (
+            self.cell_state_storage,
+            self.cell_age_storage,
+            self.cell_trail_storage,
+            self.state_readback_buffer,
+        ) = create_cell_buffers(&self.device, grid_size, self.cell_state_storage.len());
+        self.neighbor_count = create_per_cell_buffer(&self.device, "Neighbor count", grid_size);
+        self.fixed_cells = create_per_cell_buffer(&self.device, "Fixed cells", grid_size);
+        self.fixed_array = vec![0; grid_size.cells()];
+        self.bind_group = create_bind_groups(
+            &self.device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            &self.cell_state_storage,
+            &self.cell_age_storage,
+            &self.cell_trail_storage,
+            &[&self.neighbor_count, &self.fixed_cells],
+        );
+        self.visible_cells = create_per_cell_buffer(&self.device, "Visible cells", grid_size);
+        self.render_bind_group = create_render_bind_groups(
+            &self.device,
+            &self.render_bind_group_layout,
+            &self.uniform_buffer,
+            &self.cell_state_storage,
+            &self.cell_age_storage,
+            &self.cell_trail_storage,
+            &[&self.visible_cells, &self.neighbor_count],
+        );
+        self.cull_bind_group = create_cull_bind_groups(
+            &self.device,
+            &self.cull_pipeline.get_bind_group_layout(0),
+            &self.uniform_buffer,
+            &self.cell_state_storage,
+            &self.cell_trail_storage,
+            &self.visible_cells,
+            &self.draw_args,
+        );
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, grid) as u64,
+            bytemuck::cast_slice(&[grid_size.width as f32, grid_size.height as f32]),
+        );
+
+        self.grid_size = grid_size;
+        self.cell_state_array = vec![0; grid_size.cells()];
+        self.restore(self.generation, &cells);
+        Ok(())
+    }
+
+    /// Read the current generation back from the GPU. This waits for the GPU, so like
+    /// everything that calls it, it doesn't work in the browser.
+    pub fn read_cells(&self) -> Vec<u32> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(
+            &self.cell_state_storage[self.step],
+            0,
+            &self.state_readback_buffer,
+            0,
+            self.state_readback_buffer.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        bytemuck::cast_slice(&read_buffer(&self.device, &self.state_readback_buffer)).to_vec()
+    }
+
+    /// Read the state of a single cell of the current generation back from the GPU.
+    pub fn read_cell(&self, col: usize, row: usize) -> u32 {
+        let size = std::mem::size_of::<u32>() as u64;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let offset = (row * self.grid_size.width + col) as u64 * size;
+        encoder.copy_buffer_to_buffer(
+            &self.cell_state_storage[self.step],
+            offset,
+            &buffer,
+            0,
+            size,
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        bytemuck::pod_read_unaligned(&read_buffer(&self.device, &buffer))
+    }
+
+    /// Start reading the current generation back without waiting for the GPU.
+    pub fn read_cells_async(&self) -> PendingRead {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cell state async readback"),
+            size: byte_length(&self.cell_state_array),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(
+            &self.cell_state_storage[self.step],
+            0,
+            &buffer,
+            0,
+            buffer.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        PendingRead::new(buffer)
+    }
+
+    /// Number of live cells in the current generation.
+    pub fn population(&self) -> u64 {
+        self.read_cells().iter().filter(|&&cell| cell == 1).count() as u64
+    }
+
+    /// Rebuild the compute pipeline from new shader source.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), wgpu::Error> {
+        let device = &self.device;
+        crate::gpu::with_validation(device, || {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Game of Life simulation shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    simulation_shader_source(source, self.workgroup_size).into(),
+                ),
+            });
+            create_simulation_pipelines(device, &self.pipeline_layout, &module)
+        })
+        .map(|(simulation, count)| {
+            self.simulation_pipeline = simulation;
+            self.count_pipeline = count;
+        })
+    }
+}
@@ -0,0 +1,115 @@
+//! Snapshots of the grid, as binary `.life` files or as PNG images.
+//!
+//! The file starts with a 16 byte header: the magic `LIFE`, a format version,
+//! the grid width and the grid height, followed by one little endian `u32`
+//! per cell in row-major order.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{load_error::LoadError, simulation::GridSize};
+
+const MAGIC: &[u8; 4] = b"LIFE";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 16;
+
+pub fn write(path: &Path, width: u32, height: u32, cells: &[u32]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    for value in [VERSION, width, height] {
+        file.write_all(&value.to_le_bytes())?;
+    }
+    for cell in cells {
+        file.write_all(&cell.to_le_bytes())?;
+    }
+    file.flush()
+}
+
+/// Read a snapshot, returning the grid width, height and cells.
+pub fn read(path: &Path) -> Result<(u32, u32, Vec<u32>), LoadError> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    if data.len() < 4 || &data[..4] != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+    if data.len() < HEADER_SIZE {
+        return Err(LoadError::Truncated {
+            expected: HEADER_SIZE,
+            found: data.len(),
+        });
+    }
+    let word = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+    let (version, width, height) = (word(4), word(8), word(12));
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let len = width as usize * height as usize;
+    let (expected, found) = (HEADER_SIZE + len * std::mem::size_of::<u32>(), data.len());
+    if found < expected {
+        return Err(LoadError::Truncated { expected, found });
+    }
+    if found > expected {
+        return Err(LoadError::TrailingData { expected, found });
+    }
+    let cells = (0..len).map(|i| word(HEADER_SIZE + 4 * i)).collect();
+
+    Ok((width, height, cells))
+}
+
+/// Write the cells as an image with one pixel per cell, white for live cells
+/// and black otherwise. Row 0 of the grid is the bottom row of the image, as
+/// it is drawn in the window.
+pub fn write_png(path: &Path, width: u32, height: u32, cells: &[u32]) -> image::ImageResult<()> {
+    let pixels: Vec<u8> = cells
+        .chunks_exact(width as usize)
+        .rev()
+        .flatten()
+        .map(|&cell| if cell == 1 { 255 } else { 0 })
+        .collect();
+    image::save_buffer(path, &pixels, width, height, image::ColorType::L8)
+}
+
+/// Read an image as a grid of `grid_size` cells, with pixels darker than `threshold`,
+/// from 0 to 1, alive. Images with a different aspect ratio than the grid are cropped
+/// around their center first, then scaled to the grid without smoothing. Returns the
+/// original width and height of the image along with the cells.
+pub fn read_image(
+    path: &Path,
+    grid_size: GridSize,
+    threshold: f32,
+) -> Result<(u32, u32, Vec<u32>), LoadError> {
+    let image = image::open(path)?.to_luma8();
+    let (width, height) = image.dimensions();
+    let (columns, rows) = (grid_size.width as u64, grid_size.height as u64);
+    let crop_width = (width as u64).min(height as u64 * columns / rows).max(1) as u32;
+    let crop_height = (height as u64).min(width as u64 * rows / columns).max(1) as u32;
+    let cropped = image::imageops::crop_imm(
+        &image,
+        (width - crop_width) / 2,
+        (height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    )
+    .to_image();
+    let scaled = image::imageops::resize(
+        &cropped,
+        columns as u32,
+        rows as u32,
+        image::imageops::FilterType::Nearest,
+    );
+
+    // The top row of the image is the top row of the grid, which is the last one.
+    let cutoff = threshold * 255.0;
+    let cells = scaled
+        .rows()
+        .rev()
+        .flatten()
+        .map(|pixel| ((pixel.0[0] as f32) < cutoff) as u32)
+        .collect();
+    Ok((width, height, cells))
+}
@@ -0,0 +1,1294 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    window::{Fullscreen, Window},
+};
+
+use crate::{
+    args::Args,
+    camera::Camera,
+    cycles::CycleDetector,
+    generation_label::GenerationLabel,
+    gpu::{self, read_buffer, PendingRead},
+    history::{History, Snapshot},
+    minimap::Minimap,
+    pixelated::Pixelated,
+    render_target::RenderTarget,
+    renderer::{Palette, Renderer},
+    simulation::{Boundary, GridSize, Simulation},
+    ui::{Controls, Ui},
+};
+// Patterns are copied and pasted through the clipboard, which the browser doesn't have.
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pattern::Pattern;
+
+/// How frames are presented to the window, see `wgpu::PresentMode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Wait for vertical blank, capping the framerate at the refresh rate.
+    #[default]
+    Fifo,
+    /// Wait for vertical blank, but replace queued frames for lower latency.
+    Mailbox,
+    /// Present right away, uncapped but possibly tearing.
+    Immediate,
+}
+
+impl std::str::FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(PresentMode::Fifo),
+            "mailbox" => Ok(PresentMode::Mailbox),
+            "immediate" => Ok(PresentMode::Immediate),
+            _ => Err("expected 'fifo', 'mailbox' or 'immediate'".to_string()),
+        }
+    }
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+/// The center of the live `cells`, in cells from the bottom left corner of the grid.
+///
+/// On a wrapping grid the center is the circular mean, so a pattern crossing an edge
+/// stays in one piece. There is no center when no cells are alive, or when they are
+/// spread out over the grid and the center would jump around with each generation.
+fn centroid(cells: &[u32], grid_size: GridSize, boundary: Boundary) -> Option<[f32; 2]> {
+    let width = grid_size.width;
+    let live: Vec<[f32; 2]> = cells
+        .iter()
+        .enumerate()
+        .filter(|&(_, &cell)| cell == 1)
+        .map(|(i, _)| [(i % width) as f32 + 0.5, (i / width) as f32 + 0.5])
+        .collect();
+    if live.is_empty() {
+        return None;
+    }
+    let count = live.len() as f32;
+    let sides = [grid_size.width as f32, grid_size.height as f32];
+
+    match boundary {
+        Boundary::Dead | Boundary::Border => {
+            let mean = |axis: usize| live.iter().map(|cell| cell[axis]).sum::<f32>() / count;
+            let center = [mean(0), mean(1)];
+            let variance = live
+                .iter()
+                .map(|cell| (cell[0] - center[0]).powi(2) + (cell[1] - center[1]).powi(2))
+                .sum::<f32>()
+                / count;
+            (variance.sqrt() < sides[0].min(sides[1]) / 4.0).then_some(center)
+        }
+        Boundary::Wrap => {
+            let mut center = [0.0; 2];
+            for ((axis, center), side) in center.iter_mut().enumerate().zip(sides) {
+                let angles = live
+                    .iter()
+                    .map(|cell| cell[axis] / side * std::f32::consts::TAU);
+                let (sin, cos) = angles.fold((0.0, 0.0), |(sin, cos), angle| {
+                    (sin + angle.sin(), cos + angle.cos())
+                });
+                // The mean direction has length 1 when all cells are in one column or
+                // row, and close to 0 when they are evenly spread out.
+                if sin.hypot(cos) / count < 0.5 {
+                    return None;
+                }
+                *center = (sin.atan2(cos) / std::f32::consts::TAU * side).rem_euclid(side);
+            }
+            Some(center)
+        }
+    }
+}
+
+/// How the position based palettes run across the grid, cycled through with the V key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Gradient {
+    #[default]
+    Diagonal,
+    Horizontal,
+    Vertical,
+    Radial,
+}
+
+impl Gradient {
+    fn next(self) -> Gradient {
+        match self {
+            Gradient::Diagonal => Gradient::Horizontal,
+            Gradient::Horizontal => Gradient::Vertical,
+            Gradient::Vertical => Gradient::Radial,
+            Gradient::Radial => Gradient::Diagonal,
+        }
+    }
+}
+
+/// An opaque color given as `RRGGBB` hex on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Default for Color {
+    /// The dark blue from the original codelab.
+    fn default() -> Self {
+        Color { r: 0, g: 0, b: 102 }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 6 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("expected RRGGBB hex like 000066".to_string());
+        }
+        let channel = |i: usize| u8::from_str_radix(&s[i..i + 2], 16).unwrap();
+        Ok(Color {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+        })
+    }
+}
+
+/// Decode an sRGB channel in 0..1 to linear light.
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear channel in 0..1 as sRGB.
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl Color {
+    const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+
+    fn inverted(self) -> Color {
+        Color {
+            r: 255 - self.r,
+            g: 255 - self.g,
+            b: 255 - self.b,
+        }
+    }
+
+    /// The color in linear light, for clearing an sRGB surface.
+    fn linear(self) -> wgpu::Color {
+        let wgpu::Color { r, g, b, a } = self.into();
+        wgpu::Color {
+            r: srgb_to_linear(r),
+            g: srgb_to_linear(g),
+            b: srgb_to_linear(b),
+            a,
+        }
+    }
+}
+
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        wgpu::Color {
+            r: color.r as f64 / 255.0,
+            g: color.g as f64 / 255.0,
+            b: color.b as f64 / 255.0,
+            a: 1.0,
+        }
+    }
+}
+
+/// The window and everything needed to draw the simulation into it.
+pub struct State {
+    simulation: Simulation,
+    // Run with the `--compare` rule and drawn in the right half of the window.
+    comparison: Option<Simulation>,
+    surface: wgpu::Surface,
+    // The surface's size and format, and with `--msaa` the texture of several samples per
+    // pixel the frame is drawn into and resolved into the window.
+    target: RenderTarget,
+    adapter_info: wgpu::AdapterInfo,
+    renderer: Renderer,
+    // With `--pixelated`, cells are drawn into textures that are then scaled up to the window.
+    pixelated: Option<Pixelated>,
+    minimap: Minimap,
+    show_minimap: bool,
+    generation_label: GenerationLabel,
+    show_generation: bool,
+    // Off to only advance the generations, without drawing them.
+    rendering: bool,
+    camera: Camera,
+    follow: bool,
+    // With `--detect-cycles`, the last generations of the first simulation, added by its hook.
+    cycles: Option<Rc<RefCell<CycleDetector>>>,
+    background: Color,
+    // Colors swapped for their opposites, and only live cells in white over black.
+    invert: bool,
+    high_contrast: bool,
+    palette: Palette,
+    gradient: Gradient,
+    history: History,
+    brush_size: usize,
+    // Cell shown in the hover readout.
+    hovered: Option<(usize, usize)>,
+    // The grid as last read back for the hover readout, with its generation, and the
+    // readback of a newer one, finished a frame or so later.
+    hover_cells: Option<(u64, Vec<u32>)>,
+    hover_read: Option<(u64, PendingRead)>,
+    // Readback of the generation to add to the graph next, finished a frame or so later.
+    population_read: Option<(u64, PendingRead)>,
+    ui: Ui,
+    // Size of the window before going fullscreen, restored when leaving it. It is kept in
+    // logical pixels in case the window goes fullscreen on a monitor of another scale.
+    windowed_size: Option<LogicalSize<f64>>,
+    // Declared last so it is dropped after the surface that refers to it.
+    window: Window,
+}
+
+impl State {
+    pub async fn new(
+        window: Window,
+        args: &Args,
+        initial_cells: Option<Vec<u32>>,
+    ) -> anyhow::Result<State> {
+        let instance = gpu::create_instance(args.backend);
+
+        // Surface is unique to the Rust API of wgpu. In the WebGPU specification, GPUCanvasContext serves a similar role.
+        // Source: https://docs.rs/wgpu/latest/wgpu/struct.Surface.html
+        //
+        // ```js
+        // const context = canvas.getContext("webgpu");
+        // ```
+        let surface = unsafe { instance.create_surface(&window) }
+            .context("Failed to create a surface for the window")?;
+
+        let (adapter, device, queue) =
+            gpu::request_device(&instance, args.backend, args.gpu, Some(&surface)).await?;
+
+        // ```js
+        // const canvasFormat = navigator.gpu.getPreferredCanvasFormat();
+        // context.configure({
+        //     device: device,
+        //     format: canvasFormat,
+        // });
+        // ```
+        // The surface has a pixel for each physical pixel of the window, so the grid stays
+        // sharp on HiDPI displays.
+        let size = window.inner_size();
+        log::info!(
+            "Window {}x{} at scale factor {}",
+            size.width,
+            size.height,
+            window.scale_factor()
+        );
+        let mut config = surface
+            .get_default_config(&adapter, size.width, size.height)
+            .context("The window surface is not supported by the adapter")?;
+        let capabilities = surface.get_capabilities(&adapter);
+        // Prefer an sRGB format, so blending happens in linear light and colors
+        // look the same as on the web. Every pipeline drawing to the window is built
+        // for this format.
+        config.format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(wgpu::TextureFormat::is_srgb)
+            .unwrap_or(config.format);
+        log::info!("Surface format {:?}", config.format);
+        let present_modes = capabilities.present_modes;
+        config.present_mode = if present_modes.contains(&args.present_mode.into()) {
+            args.present_mode.into()
+        } else {
+            log::warn!(
+                "Present mode {:?} is not supported, falling back to Fifo",
+                args.present_mode
+            );
+            wgpu::PresentMode::Fifo
+        };
+        let effect = match config.present_mode {
+            wgpu::PresentMode::Mailbox => "vsync with the lowest latency",
+            wgpu::PresentMode::Immediate => "no vsync, the framerate is uncapped",
+            _ => "vsync, the framerate is capped at the refresh rate",
+        };
+        log::info!("Present mode {:?}: {effect}", config.present_mode);
+        surface.configure(&device, &config);
+
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        let comparison = args
+            .compare
+            .map(|rule| {
+                Simulation::new(
+                    device.clone(),
+                    queue.clone(),
+                    &args.settings(),
+                    rule,
+                    initial_cells.clone(),
+                )
+            })
+            .transpose()?;
+        let mut simulation =
+            Simulation::new(device, queue, &args.settings(), args.rule, initial_cells)?;
+        let cycles = args.detect_cycles.then(|| {
+            let cycles = Rc::new(RefCell::new(CycleDetector::default()));
+            let detector = cycles.clone();
+            simulation.on_generation(true, move |generation, cells| {
+                let cells = cells.expect("The hook reads the cells");
+                match detector.borrow_mut().push(cells) {
+                    Some(1) => log::info!("Settled into still lifes at generation {generation}"),
+                    Some(period) => log::info!(
+                        "Settled into a cycle of period {period} at generation {generation}"
+                    ),
+                    None => {}
+                }
+            });
+            cycles
+        });
+        // Stepping back would have to go through both simulations, so there is no
+        // history when comparing.
+        let depth = if comparison.is_some() {
+            0
+        } else {
+            args.history
+        };
+        let history = History::new(depth);
+        if history.is_enabled() {
+            let bytes = depth * args.grid_size.cells() * 4;
+            log::info!(
+                "Keeping {} generations of history, up to {:.1} MiB",
+                args.history,
+                bytes as f64 / (1024.0 * 1024.0)
+            );
+        }
+        let mut camera = Camera::default();
+        let viewports = if comparison.is_some() { 2 } else { 1 };
+        camera.set_window_size(size.width / viewports, size.height);
+        camera.set_grid_size(args.grid_size.width, args.grid_size.height);
+        let pixelated = args.pixelated.then(|| {
+            Pixelated::new(
+                simulation.device(),
+                config.format,
+                args.grid_size,
+                viewports as usize,
+            )
+        });
+
+        let ui = Ui::new(&window, simulation.device(), config.format);
+
+        let sample_count = if gpu::supports_sample_count(
+            &adapter,
+            simulation.device(),
+            config.format,
+            args.msaa,
+        ) {
+            args.msaa
+        } else {
+            log::warn!(
+                "{} samples per pixel are not supported for {:?}, drawing without MSAA",
+                args.msaa,
+                config.format
+            );
+            1
+        };
+        let renderer = Renderer::new_multisampled(&simulation, config.format, sample_count);
+        let minimap = Minimap::new(&simulation, config.format, sample_count);
+        let generation_label = GenerationLabel::new(
+            simulation.device(),
+            simulation.queue(),
+            config.format,
+            sample_count,
+        );
+        let target = RenderTarget::new(simulation.device(), config, sample_count);
+
+        let mut state = State {
+            simulation,
+            comparison,
+            surface,
+            target,
+            adapter_info: adapter.get_info(),
+            renderer,
+            pixelated,
+            minimap,
+            show_minimap: false,
+            generation_label,
+            show_generation: args.show_generation,
+            rendering: true,
+            camera,
+            follow: args.follow,
+            cycles,
+            background: args.background,
+            invert: false,
+            high_contrast: false,
+            palette: args.palette,
+            gradient: Gradient::default(),
+            history,
+            brush_size: 1,
+            hovered: None,
+            hover_cells: None,
+            hover_read: None,
+            population_read: None,
+            ui,
+            windowed_size: None,
+            window,
+        };
+        state.update_view();
+        if args.warmup {
+            state.warm_up();
+        }
+        Ok(state)
+    }
+
+    /// Step and draw the grid once without showing it, so the pipelines are compiled before
+    /// the first frame instead of holding it up.
+    fn warm_up(&mut self) {
+        let start = instant::Instant::now();
+        for simulation in self.simulations_mut() {
+            simulation.warm_up();
+        }
+
+        let device = self.simulation.device();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Warmup target"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: self.renderer.sample_count(),
+            dimension: wgpu::TextureDimension::D2,
+            format: self.target.config().format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        for simulation in self.simulations() {
+            self.renderer
+                .render_to(&mut encoder, &view, simulation, self.clear_color());
+        }
+        self.simulation.queue().submit(Some(encoder.finish()));
+        device.poll(wgpu::Maintain::Wait);
+        log::info!("Warmed up the pipelines in {:?}", start.elapsed());
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// The simulation together with the one it is compared with, if any.
+    fn simulations(&self) -> impl Iterator<Item = &Simulation> {
+        std::iter::once(&self.simulation).chain(&self.comparison)
+    }
+
+    fn simulations_mut(&mut self) -> impl Iterator<Item = &mut Simulation> {
+        std::iter::once(&mut self.simulation).chain(&mut self.comparison)
+    }
+
+    /// Number of side by side viewports the window is split into.
+    fn viewports(&self) -> u32 {
+        if self.comparison.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Number of generations since the grid was last seeded.
+    pub fn generation(&self) -> u64 {
+        self.simulation.generation()
+    }
+
+    /// GPU time of the last measured compute pass of the simulation, see
+    /// [`Simulation::take_gpu_time`].
+    pub fn take_gpu_time(&mut self) -> Option<Duration> {
+        self.simulation.take_gpu_time()
+    }
+
+    /// Set how far along the way to the next generation the display is, from 0 to 1.
+    pub fn set_progress(&self, progress: f32) {
+        for simulation in self.simulations() {
+            simulation.set_progress(progress);
+        }
+    }
+
+    /// Advance the simulation by one generation.
+    pub fn update(&mut self) {
+        if self.history.is_enabled() {
+            self.history.push(self.snapshot());
+        }
+        for simulation in self.simulations_mut() {
+            simulation.update();
+        }
+        self.follow_cells();
+    }
+
+    /// Advance the simulation by `generations` generations in one submission. History
+    /// needs every generation, so while it is kept they are submitted one by one.
+    pub fn advance(&mut self, generations: u32) {
+        if self.history.is_enabled() {
+            for _ in 0..generations {
+                self.update();
+            }
+            return;
+        }
+        for simulation in self.simulations_mut() {
+            simulation.advance(generations);
+        }
+        self.follow_cells();
+    }
+
+    /// With `--follow`, pan towards the center of the live cells. When comparing, the
+    /// camera follows the first simulation.
+    fn follow_cells(&mut self) {
+        if !self.follow {
+            return;
+        }
+        let grid_size = self.simulation.grid_size();
+        let cells = self.simulation.read_cells();
+        if let Some([x, y]) = centroid(&cells, grid_size, self.simulation.boundary()) {
+            let (width, height) = (grid_size.width as f32, grid_size.height as f32);
+            self.camera
+                .follow([x / width * 2.0 - 1.0, y / height * 2.0 - 1.0]);
+            self.update_view();
+        }
+    }
+
+    /// Whether `--detect-cycles` noticed the grid settling since it last changed.
+    pub fn is_settled(&self) -> bool {
+        self.cycles
+            .as_ref()
+            .is_some_and(|cycles| cycles.borrow().is_settled())
+    }
+
+    /// Forget the cells read back for the hover readout and the generations kept to detect
+    /// cycles, after the grid changed.
+    fn grid_changed(&mut self) {
+        self.hover_cells = None;
+        self.hover_read = None;
+        if let Some(cycles) = &self.cycles {
+            cycles.borrow_mut().clear();
+        }
+    }
+
+    /// Re-seed the grid with a fresh random state of the given density.
+    pub fn reseed(&mut self, density: f64) {
+        self.history.clear();
+        self.grid_changed();
+        for simulation in self.simulations_mut() {
+            simulation.reseed(density);
+        }
+    }
+
+    /// Start over from a grid of only live or only dead cells.
+    pub fn fill(&mut self, alive: bool) {
+        self.history.clear();
+        self.grid_changed();
+        for simulation in self.simulations_mut() {
+            simulation.fill(alive);
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        (self.simulation.generation(), self.simulation.read_cells())
+    }
+
+    /// Go back one generation in the history, if there is one.
+    pub fn step_back(&mut self) {
+        if !self.history.is_enabled() {
+            return log::info!("No history to step back through, pass '--history N' to keep it");
+        }
+        let current = self.snapshot();
+        if let Some((generation, cells)) = self.history.back(current) {
+            self.simulation.restore(generation, &cells);
+            self.grid_changed();
+        }
+    }
+
+    /// Go forward one generation in the history, returning whether there was
+    /// one to go to.
+    pub fn step_forward(&mut self) -> bool {
+        if !self.history.is_enabled() {
+            return false;
+        }
+        let current = self.snapshot();
+        match self.history.forward(current) {
+            Some((generation, cells)) => {
+                self.simulation.restore(generation, &cells);
+                self.grid_changed();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draw live cells in a single color instead of by position.
+    ///
+    /// The color is in linear RGB, as edited in the control panel.
+    pub fn set_alive_color(&self, color: Option<[f32; 3]>) {
+        let color = color.map(|color| color.map(linear_to_srgb));
+        for simulation in self.simulations() {
+            simulation.set_alive_color(color);
+        }
+    }
+
+    /// Switch to the next palette for live cells.
+    pub fn cycle_palette(&mut self) {
+        self.palette = self.palette.next();
+        for simulation in self.simulations() {
+            simulation.set_palette(self.palette as u32);
+        }
+        log::info!("Palette {:?}", self.palette);
+    }
+
+    /// Change the number of columns and rows of the grid, keeping the pattern in the
+    /// top-left corner.
+    pub fn resize_grid(&mut self, grid_size: GridSize) {
+        for simulation in self.simulations_mut() {
+            if let Err(err) = simulation.resize_grid(grid_size) {
+                log::error!("Failed to resize the grid: {err}");
+                return;
+            }
+        }
+        let viewports = self.viewports() as usize;
+        if let Some(pixelated) = &mut self.pixelated {
+            pixelated.resize(self.simulation.device(), grid_size, viewports);
+        }
+        self.camera.set_grid_size(grid_size.width, grid_size.height);
+        self.update_view();
+        self.history.clear();
+        self.grid_changed();
+        log::info!("Grid size {grid_size}");
+    }
+
+    /// Switch to the next direction for the position based palettes.
+    pub fn cycle_gradient(&mut self) {
+        self.gradient = self.gradient.next();
+        for simulation in self.simulations() {
+            simulation.set_gradient(self.gradient as u32);
+        }
+        log::info!("Gradient {:?}", self.gradient);
+    }
+
+    /// Switch the simulation to the next of the preset rules, keeping the grid. When
+    /// comparing, the rule it is compared with stays the same.
+    pub fn cycle_rule(&mut self) {
+        let rule = self.simulation.rule().next_preset();
+        self.simulation.set_rule(rule);
+        log::info!("Rule {} ({rule})", rule.name().unwrap_or("custom"));
+    }
+
+    /// Show or hide the whole grid in the top right corner, with the part in the window
+    /// outlined.
+    pub fn toggle_minimap(&mut self) {
+        self.show_minimap = !self.show_minimap;
+        log::info!("Minimap {}", if self.show_minimap { "on" } else { "off" });
+    }
+
+    /// Show or hide the generation number in the bottom left corner. It is drawn with
+    /// the grid, so it also shows in screenshots and recordings.
+    pub fn toggle_generation_label(&mut self) {
+        self.show_generation = !self.show_generation;
+        log::info!(
+            "Generation label {}",
+            if self.show_generation { "on" } else { "off" }
+        );
+    }
+
+    /// Stop drawing frames to leave the GPU to the simulation, or draw the current
+    /// generation again.
+    pub fn toggle_rendering(&mut self) {
+        self.rendering = !self.rendering;
+        log::info!("Rendering {}", if self.rendering { "on" } else { "off" });
+        self.window.request_redraw();
+    }
+
+    /// Whether frames are drawn, see [`State::toggle_rendering`].
+    pub fn is_rendering(&self) -> bool {
+        self.rendering
+    }
+
+    /// Swap every color, including the background, for its opposite.
+    pub fn toggle_invert(&mut self) {
+        self.invert = !self.invert;
+        for simulation in self.simulations() {
+            simulation.set_invert(self.invert);
+        }
+        log::info!("Inverted colors {}", if self.invert { "on" } else { "off" });
+    }
+
+    /// Switch to white live cells on black and back, for washed out projectors.
+    pub fn toggle_high_contrast(&mut self) {
+        self.high_contrast = !self.high_contrast;
+        for simulation in self.simulations() {
+            simulation.set_high_contrast(self.high_contrast);
+        }
+        log::info!(
+            "High contrast {}",
+            if self.high_contrast { "on" } else { "off" }
+        );
+    }
+
+    /// The color the window is cleared to, after high contrast and inversion.
+    fn clear_color(&self) -> wgpu::Color {
+        let mut color = if self.high_contrast {
+            Color::BLACK
+        } else {
+            self.background
+        };
+        if self.invert {
+            color = color.inverted();
+        }
+        if self.target.config().format.is_srgb() {
+            color.linear()
+        } else {
+            color.into()
+        }
+    }
+
+    /// Switch between coloring cells by their state and by their number of live neighbors,
+    /// which shows why cells are born and die. The simulation isn't affected.
+    pub fn toggle_heatmap(&mut self) {
+        let heatmap = !self.simulation.heatmap();
+        for simulation in self.simulations_mut() {
+            simulation.set_heatmap(heatmap);
+        }
+        log::info!("Neighbor heatmap {}", if heatmap { "on" } else { "off" });
+    }
+
+    /// Switch between borderless fullscreen on the current monitor and the window at its
+    /// previous size. The surface follows with the resize event of the new size.
+    pub fn toggle_fullscreen(&mut self) {
+        match self.windowed_size.take() {
+            Some(size) => {
+                self.window.set_fullscreen(None);
+                self.window.set_inner_size(size);
+            }
+            None => {
+                let size = self.window.inner_size();
+                self.windowed_size = Some(size.to_logical(self.window.scale_factor()));
+                self.window
+                    .set_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.ui.toggle_help();
+    }
+
+    /// Show or hide the population graph.
+    pub fn toggle_population_graph(&mut self) {
+        let graph = self.ui.population_graph();
+        graph.visible = !graph.visible;
+    }
+
+    /// Add the population of the last finished readback to the graph and read
+    /// back the current generation if it isn't in the graph yet. Generations
+    /// advanced while a readback is in flight are skipped.
+    fn sample_population(&mut self) {
+        let device = self.simulation.device();
+        let graph = self.ui.population_graph();
+        if let Some((generation, pending)) = &self.population_read {
+            let Some(data) = pending.try_read(device) else {
+                return;
+            };
+            let cells: &[u32] = bytemuck::cast_slice(&data);
+            let population = cells.iter().filter(|&&cell| cell == 1).count() as u64;
+            graph.push(*generation, population);
+            self.population_read = None;
+        }
+
+        let generation = self.simulation.generation();
+        if graph.visible && graph.last_generation() != Some(generation) {
+            self.population_read = Some((generation, self.simulation.read_cells_async()));
+        }
+    }
+
+    /// Pass a window event to the control panel, returning whether it used it.
+    pub fn ui_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        self.ui.on_event(event)
+    }
+
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.target.config().present_mode
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.target.is_minimized()
+    }
+
+    /// Reconfigure the surface for the new window size, or stop drawing while it is empty.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let device = self.simulation.device();
+        if !self.target.resize(device, size.width, size.height) {
+            log::debug!("Window minimized, not drawing");
+            return;
+        }
+
+        self.surface.configure(device, self.target.config());
+        self.ui.resize(size);
+        self.camera
+            .set_window_size(size.width / self.viewports(), size.height);
+        self.update_view();
+    }
+
+    fn update_view(&self) {
+        // Pixelated cells fill their texture, which is moved by the camera instead.
+        let view = match &self.pixelated {
+            Some(pixelated) => {
+                pixelated.set_view(self.simulation.queue(), self.camera.view());
+                [1.0, 1.0, 0.0, 0.0]
+            }
+            None => self.camera.view(),
+        };
+        self.minimap
+            .set_view(self.simulation.queue(), self.camera.view());
+        for simulation in self.simulations() {
+            simulation.set_view(view);
+        }
+    }
+
+    /// Draw the current generation and the control panel to the window. A lost
+    /// or outdated surface is reconfigured and the frame skipped, only running
+    /// out of memory is returned as an error.
+    pub fn render(&mut self, controls: &mut Controls) -> Result<(), wgpu::SurfaceError> {
+        if self.target.is_minimized() {
+            return Ok(());
+        }
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(err @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                log::info!("Reconfiguring the surface: {err}");
+                self.surface
+                    .configure(self.simulation.device(), self.target.config());
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                log::warn!("Timed out waiting for the surface, skipping the frame");
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // ```js
+        // const encoder = device.createCommandEncoder();
+        // ```
+        let mut encoder = self
+            .simulation
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.sample_population();
+        self.draw(&mut encoder, &view);
+        self.ui.draw(
+            &self.window,
+            self.simulation.device(),
+            self.simulation.queue(),
+            &mut encoder,
+            &view,
+            controls,
+        );
+
+        // ```js
+        // device.queue.submit([encoder.finish()]);
+        // ```
+        self.simulation.queue().submit(Some(encoder.finish()));
+
+        // Present the the work that has been submitted into the queue
+        frame.present();
+
+        Ok(())
+    }
+
+    /// Record the render pass that draws the current generation into `view`.
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        // ```js
+        // const pass = encoder.beginRenderPass({
+        //     colorAttachments: [{
+        //         view: context.getCurrentTexture().createView(),
+        //         loadOp: "clear",
+        //         clearValue: { r: 0, g: 0, b: 0.4, a: 1 }, // New line
+        //         storeOp: "store",
+        //     }],
+        // });
+        // ```
+        // Only the cells that show anything are drawn, listed by a compute pass first.
+        for simulation in self.simulations() {
+            self.renderer.prepare(encoder, simulation);
+        }
+
+        if let Some(pixelated) = &self.pixelated {
+            for (i, simulation) in self.simulations().enumerate() {
+                let mut pass = self.begin_pass(encoder, pixelated.target(i), None);
+                self.renderer.draw(&mut pass, simulation);
+            }
+        }
+
+        let mut pass = match self.target.msaa() {
+            Some(target) => self.begin_pass(encoder, target, Some(view)),
+            None => self.begin_pass(encoder, view, None),
+        };
+        // When comparing, each simulation is drawn into its own half of the target.
+        let width = self.target.config().width as f32 / self.viewports() as f32;
+        let height = self.target.config().height as f32;
+        for (i, simulation) in self.simulations().enumerate() {
+            pass.set_viewport(i as f32 * width, 0.0, width, height, 0.0, 1.0);
+            match &self.pixelated {
+                Some(pixelated) => pixelated.draw(&mut pass, i),
+                None => self.renderer.draw(&mut pass, simulation),
+            }
+            if self.show_minimap {
+                self.minimap.draw(
+                    &mut pass,
+                    simulation,
+                    [i as f32 * width, 0.0, width, height],
+                );
+            }
+        }
+        if self.show_generation {
+            self.generation_label.draw(
+                &mut pass,
+                self.simulation.queue(),
+                self.generation(),
+                [
+                    self.target.config().width as f32,
+                    self.target.config().height as f32,
+                ],
+            );
+        }
+
+        // ```js
+        // pass.end()
+        // ```
+        drop(pass);
+    }
+
+    /// Begin a render pass into `view`, cleared to the background color, and resolved into
+    /// `resolve_target` when `view` is multisampled.
+    fn begin_pass<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        resolve_target: Option<&'a wgpu::TextureView>,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color()),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// Render the current generation into an offscreen texture and read it
+    /// back as tightly packed RGBA8 rows, top row first.
+    pub fn capture_frame(&self) -> Result<(u32, u32, Vec<u8>), String> {
+        let swap_red_blue = match self.target.config().format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(format!("Capturing {format:?} frames is not supported")),
+        };
+
+        let (width, height) = (self.target.config().width, self.target.config().height);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self
+            .simulation
+            .device()
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Capture texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.target.config().format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+
+        // Rows in a texture to buffer copy must be aligned to 256 bytes.
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self
+            .simulation
+            .device()
+            .create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Capture readback"),
+                size: (padded_bytes_per_row * height) as u64,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false, // WebGPU defaults to false `boolean mappedAtCreation = false;`
+            });
+
+        let mut encoder = self
+            .simulation
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.draw(
+            &mut encoder,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        self.simulation.queue().submit(Some(encoder.finish()));
+
+        // Strip the row padding.
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in
+            read_buffer(self.simulation.device(), &buffer).chunks(padded_bytes_per_row as usize)
+        {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+
+        if swap_red_blue {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok((width, height, pixels))
+    }
+
+    /// Save the current frame as a PNG.
+    pub fn screenshot(&self, path: &std::path::Path) -> Result<(), String> {
+        let (width, height, pixels) = self.capture_frame()?;
+        image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Width and height in physical pixels of the part of the surface a simulation is drawn
+    /// in, which cursor positions are given in too.
+    fn viewport_size(&self) -> [f64; 2] {
+        [
+            self.target.config().width as f64 / self.viewports() as f64,
+            self.target.config().height as f64,
+        ]
+    }
+
+    /// Convert a window position to normalized device coordinates within its viewport.
+    ///
+    /// Window coordinates start at the top-left, while NDC has y pointing up.
+    fn to_ndc(&self, position: PhysicalPosition<f64>) -> [f32; 2] {
+        let [width, height] = self.viewport_size();
+        [
+            (position.x.rem_euclid(width) / width * 2.0 - 1.0) as f32,
+            (1.0 - position.y / height * 2.0) as f32,
+        ]
+    }
+
+    /// Zoom in or out by `steps` scroll wheel notches around a window position.
+    pub fn zoom(&mut self, position: PhysicalPosition<f64>, steps: f32) {
+        const ZOOM_PER_STEP: f32 = 1.1;
+        self.camera
+            .zoom_at(self.to_ndc(position), ZOOM_PER_STEP.powf(steps));
+        self.update_view();
+    }
+
+    /// Move the grid along with the cursor going from `from` to `to`.
+    ///
+    /// The distance is taken in pixels, so the cursor can cross from one viewport into the other.
+    pub fn pan(&mut self, from: PhysicalPosition<f64>, to: PhysicalPosition<f64>) {
+        let [width, height] = self.viewport_size();
+        self.camera.pan([
+            ((to.x - from.x) / width * 2.0) as f32,
+            ((from.y - to.y) / height * 2.0) as f32,
+        ]);
+        self.update_view();
+    }
+
+    /// The column and row of the cell under a window position, if any.
+    pub fn cell_at(&self, position: PhysicalPosition<f64>) -> Option<(usize, usize)> {
+        let grid_size = self.simulation.grid_size();
+
+        // Row 0 of the grid is drawn at the bottom.
+        let [x, y] = self.camera.to_grid(self.to_ndc(position));
+        let (x, y) = ((x + 1.0) / 2.0, (y + 1.0) / 2.0);
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+        let col = ((x * grid_size.width as f32) as usize).min(grid_size.width - 1);
+        let row = ((y * grid_size.height as f32) as usize).min(grid_size.height - 1);
+        Some((col, row))
+    }
+
+    /// Set the width of the square brush in cells.
+    pub fn set_brush_size(&mut self, size: usize) {
+        self.brush_size = size;
+        log::info!("Brush size {size}");
+    }
+
+    /// Paint live cells, or erase them, with the brush under the given window position.
+    /// With `fixed`, the cells keep that state every generation, see [`Simulation::paint`].
+    pub fn paint(&mut self, position: PhysicalPosition<f64>, alive: bool, fixed: bool) {
+        if let Some((col, row)) = self.cell_at(position) {
+            let brush_size = self.brush_size;
+            for simulation in self.simulations_mut() {
+                simulation.paint(col, row, brush_size, alive, fixed);
+            }
+            self.history.truncate_future();
+            self.grid_changed();
+        }
+    }
+
+    /// Flip the cell under the given window position from dead to live or back, with
+    /// `fixed` as in [`State::paint`]. The browser can't read the cell back right away,
+    /// so there it is always made live.
+    pub fn toggle(&mut self, position: PhysicalPosition<f64>, fixed: bool) {
+        if let Some((col, row)) = self.cell_at(position) {
+            let alive = cfg!(target_arch = "wasm32") || self.simulation.read_cell(col, row) != 1;
+            for simulation in self.simulations_mut() {
+                simulation.paint(col, row, 1, alive, fixed);
+            }
+            self.history.truncate_future();
+            self.grid_changed();
+        }
+    }
+
+    /// The live cells of the grid as a pattern with the rule, cropped to the live cells. When
+    /// comparing, the pattern is taken from the first simulation.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pattern(&self) -> Pattern {
+        let simulation = &self.simulation;
+        let mut pattern = Pattern::from_cells(&simulation.read_cells(), simulation.grid_size());
+        pattern.rule = Some(simulation.rule());
+        pattern
+    }
+
+    /// Stamp `pattern` into the grid centered on the cell under a window position, or in
+    /// the center of the grid when the position is off the grid.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stamp(&mut self, pattern: &Pattern, position: PhysicalPosition<f64>) {
+        let grid_size = self.simulation.grid_size();
+        let (col, row) = self
+            .cell_at(position)
+            .unwrap_or((grid_size.width / 2, grid_size.height / 2));
+        for simulation in self.simulations_mut() {
+            simulation.stamp(col, row, pattern);
+        }
+        self.history.truncate_future();
+        self.grid_changed();
+    }
+
+    /// Show the coordinates and state of the cell under the cursor in the control panel,
+    /// returning whether the readout changed.
+    pub fn hover(&mut self, position: PhysicalPosition<f64>) -> bool {
+        self.hovered = self.cell_at(position);
+        self.update_hover()
+    }
+
+    /// Bring the hover readout up to date with the grid, returning whether it changed. The
+    /// grid is read back without waiting for the GPU, so the state of the cell shows as
+    /// unknown until a later call finds the read done.
+    pub fn update_hover(&mut self) -> bool {
+        if let Some((generation, pending)) = &self.hover_read {
+            if let Some(data) = pending.try_read(self.simulation.device()) {
+                self.hover_cells = Some((*generation, bytemuck::cast_slice(&data).to_vec()));
+                self.hover_read = None;
+            }
+        }
+
+        let generation = self.simulation.generation();
+        let cells = self
+            .hover_cells
+            .as_ref()
+            .filter(|(read, _)| *read == generation)
+            .map(|(_, cells)| cells);
+        // The cell can be off a grid that shrank since.
+        let size = self.simulation.grid_size();
+        let hovered = self
+            .hovered
+            .filter(|&(col, row)| col < size.width && row < size.height);
+        let hover = hovered.map(|(col, row)| {
+            let state = match cells.map(|cells| cells[row * size.width + col]) {
+                None => "?",
+                Some(0) => "dead",
+                Some(1) => "alive",
+                Some(_) => "dying",
+            };
+            format!("cell ({col}, {row}): {state}")
+        });
+        if hovered.is_some() && cells.is_none() && self.hover_read.is_none() {
+            self.hover_read = Some((generation, self.simulation.read_cells_async()));
+        }
+
+        if hover == self.ui.hover {
+            return false;
+        }
+        self.ui.hover = hover;
+        true
+    }
+
+    /// Rebuild the pipeline that uses the changed shader file. On a compile
+    /// error the last good pipeline is kept.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shader(&mut self, path: &std::path::Path) {
+        let name = path.file_name().and_then(|name| name.to_str());
+        if !matches!(name, Some("cell.wgsl" | "simulation.wgsl")) {
+            return;
+        }
+
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                log::error!("Failed to read {}: {err}", path.display());
+                return;
+            }
+        };
+
+        let result = if name == Some("cell.wgsl") {
+            self.renderer.reload_shader(&self.simulation, &source)
+        } else {
+            self.simulation.reload_shader(&source)
+        };
+
+        match result {
+            Ok(()) => log::info!("Reloaded {}", path.display()),
+            Err(err) => log::error!("Failed to reload {}: {err}", path.display()),
+        }
+    }
+}
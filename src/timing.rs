@@ -0,0 +1,256 @@
+//! Pacing of frames and simulation steps, kept out of the event loop so `--check-timing`
+//! can run it without a window.
+
+use std::time::Duration;
+
+// The same as `std::time::Instant`, which panics in the browser, outside of it.
+use instant::Instant;
+use winit::event_loop::ControlFlow;
+
+/// What to do with simulation steps that came due while a frame took longer than the
+/// update interval, chosen with `--late-steps`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LateSteps {
+    /// Run all of them in the next frame, so the simulation keeps its rate over time.
+    #[default]
+    CatchUp,
+    /// Run one and drop the rest, so the simulation slows down instead of jumping ahead.
+    Skip,
+}
+
+impl std::str::FromStr for LateSteps {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "catch-up" => Ok(LateSteps::CatchUp),
+            "skip" => Ok(LateSteps::Skip),
+            _ => Err("expected 'catch-up' or 'skip'".to_string()),
+        }
+    }
+}
+
+/// What the event loop does once it has handled the events at `now`. While paused it
+/// sleeps `paused_interval` at a time, so it still finishes saves and reloads shaders.
+/// With rendering off there are no frames to wait for the display with, so it sleeps
+/// until `next_step` instead. Otherwise it sleeps until `next_frame` when the frame cap
+/// says one is due later, or polls to draw right away.
+pub fn control_flow(
+    now: Instant,
+    next_frame: Option<Instant>,
+    next_step: Option<Instant>,
+    paused_interval: Option<Duration>,
+) -> ControlFlow {
+    let wake_up = match next_step {
+        Some(next_step) => Some(next_step).filter(|&next_step| now < next_step),
+        None => next_frame,
+    };
+    match (paused_interval, wake_up) {
+        (Some(interval), _) => ControlFlow::WaitUntil(now + interval),
+        (None, Some(wake_up)) => ControlFlow::WaitUntil(wake_up),
+        (None, None) => ControlFlow::Poll,
+    }
+}
+
+/// Tracks when the next frame is due and how many simulation steps are due, so the
+/// simulation advances at its own rate while frames are drawn at the display refresh rate.
+pub struct FrameClock {
+    // Time not yet spent on simulation steps.
+    accumulator: Duration,
+    last_frame: Instant,
+    late_steps: LateSteps,
+    // Shortest time between frames from `--max-fps`, the simulation keeps its own rate.
+    frame_interval: Option<Duration>,
+    // When the next frame is due with a capped framerate. It moves on by the frame
+    // interval from the previous deadline rather than from when the frame was drawn,
+    // so frames that start a little late don't add up to a lower framerate.
+    frame_deadline: Instant,
+}
+
+impl FrameClock {
+    /// A clock for at most `max_fps` frames per second, 0 for uncapped.
+    pub fn new(max_fps: u32, late_steps: LateSteps) -> FrameClock {
+        let now = Instant::now();
+        FrameClock {
+            accumulator: Duration::ZERO,
+            last_frame: now,
+            late_steps,
+            frame_interval: (max_fps > 0).then(|| Duration::from_secs_f64(1.0 / max_fps as f64)),
+            frame_deadline: now,
+        }
+    }
+
+    /// When the next frame is due if that is after `now`, otherwise it can be drawn right away.
+    pub fn next_frame(&self, now: Instant) -> Option<Instant> {
+        self.frame_interval
+            .map(|_| self.frame_deadline)
+            .filter(|&next_frame| now < next_frame)
+    }
+
+    /// When the next step of `interval` is due, if the clock keeps running. It can be in
+    /// the past when steps are due already.
+    pub fn next_step(&self, interval: Duration) -> Instant {
+        self.last_frame + interval.saturating_sub(self.accumulator)
+    }
+
+    /// Start a frame at `now`. While `running`, the time since the last frame is added
+    /// to the time due for steps.
+    pub fn start_frame(&mut self, now: Instant, running: bool) {
+        if running {
+            self.accumulator += now - self.last_frame;
+        }
+        self.last_frame = now;
+
+        if let Some(interval) = self.frame_interval {
+            self.frame_deadline += interval;
+            // More than a whole frame behind, start over from now instead of drawing
+            // the missed frames back to back.
+            if self.frame_deadline <= now {
+                self.frame_deadline = now + interval;
+            }
+        }
+    }
+
+    /// Don't catch up on the time since the last frame, say after a pause.
+    pub fn reset(&mut self) {
+        self.last_frame = Instant::now();
+        self.frame_deadline = self.last_frame;
+    }
+
+    /// Take up to `limit` of the steps of `interval` that are due. When more are due
+    /// than the limit allows, none are taken and the backlog is dropped rather than
+    /// falling further behind. With [`LateSteps::Skip`] at most one step is taken.
+    pub fn take_steps(&mut self, interval: Duration, limit: u32) -> u32 {
+        let due = self.accumulator.as_nanos() / interval.as_nanos();
+        if due == 0 {
+            return 0;
+        }
+        if limit == 0 {
+            self.accumulator = Duration::ZERO;
+            return 0;
+        }
+        if self.late_steps == LateSteps::Skip && due > 1 {
+            // Keep the time towards the next step, so steps stay on the interval.
+            let remainder = self.accumulator.as_nanos() % interval.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+            return 1;
+        }
+        let steps = due.min(limit as u128) as u32;
+        self.accumulator -= interval * steps;
+        steps
+    }
+
+    /// How far along the way to the next step of `interval` the clock is, from 0 to 1.
+    pub fn progress(&self, interval: Duration) -> f32 {
+        self.accumulator.as_secs_f32() / interval.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAUSED_INTERVAL: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn paused_waits_for_the_interval() {
+        let now = Instant::now();
+        let next_frame = Some(now + Duration::from_millis(5));
+        for next_frame in [None, next_frame] {
+            assert_eq!(
+                control_flow(now, next_frame, None, Some(PAUSED_INTERVAL)),
+                ControlFlow::WaitUntil(now + PAUSED_INTERVAL)
+            );
+        }
+    }
+
+    #[test]
+    fn running_waits_for_the_next_frame_or_polls() {
+        let now = Instant::now();
+        let next_frame = now + Duration::from_millis(5);
+        assert_eq!(
+            control_flow(now, Some(next_frame), None, None),
+            ControlFlow::WaitUntil(next_frame)
+        );
+        assert_eq!(control_flow(now, None, None, None), ControlFlow::Poll);
+    }
+
+    #[test]
+    fn not_rendering_waits_for_the_next_step() {
+        let now = Instant::now();
+        let next_frame = now + Duration::from_millis(5);
+        let next_step = now + Duration::from_millis(20);
+        for next_frame in [None, Some(next_frame)] {
+            assert_eq!(
+                control_flow(now, next_frame, Some(next_step), None),
+                ControlFlow::WaitUntil(next_step)
+            );
+            // A step that is due already is taken right away.
+            assert_eq!(
+                control_flow(now, next_frame, Some(now), None),
+                ControlFlow::Poll
+            );
+        }
+    }
+
+    /// Drive the event loop's decisions through a second of made up time, jumping to
+    /// each wake-up and taking 1 ms per frame, and count the frames, steps and wake-ups.
+    /// Without `rendering` the frames only take the steps that are due.
+    fn simulate(
+        max_fps: u32,
+        step_interval: Duration,
+        late_steps: LateSteps,
+        rendering: bool,
+    ) -> [u32; 3] {
+        let mut clock = FrameClock::new(max_fps, late_steps);
+        let start = Instant::now();
+        let end = start + Duration::from_secs(1);
+        let mut now = start;
+        let (mut frames, mut steps, mut wakeups) = (0, 0, 0);
+        while now < end {
+            wakeups += 1;
+            let next_step = (!rendering).then(|| clock.next_step(step_interval));
+            match control_flow(now, clock.next_frame(now), next_step, None) {
+                ControlFlow::WaitUntil(next_frame) => {
+                    assert!(next_frame > now, "Waiting until the past spins");
+                    now = next_frame;
+                }
+                _ => {
+                    clock.start_frame(now, true);
+                    frames += 1;
+                    steps += clock.take_steps(step_interval, u32::MAX);
+                    now += Duration::from_millis(1);
+                }
+            }
+        }
+        [frames, steps, wakeups]
+    }
+
+    #[test]
+    fn capped_frames_and_steps_keep_their_rates() {
+        for late_steps in [LateSteps::CatchUp, LateSteps::Skip] {
+            let [frames, steps, wakeups] =
+                simulate(100, Duration::from_millis(20), late_steps, true);
+            assert!((99..=101).contains(&frames), "{frames} frames");
+            assert!((49..=51).contains(&steps), "{steps} steps");
+            // One wake-up to wait for each frame and one to draw it, not a busy loop.
+            assert!(wakeups <= 2 * frames + 1, "{wakeups} wake-ups");
+        }
+    }
+
+    #[test]
+    fn uncapped_frames_without_rendering_wait_for_steps() {
+        for max_fps in [0, 100] {
+            let [frames, steps, wakeups] = simulate(
+                max_fps,
+                Duration::from_millis(20),
+                LateSteps::CatchUp,
+                false,
+            );
+            assert!((49..=51).contains(&steps), "{steps} steps");
+            // Only woken up to take each step, not spinning through empty frames.
+            assert!(frames <= steps + 1, "{frames} frames");
+            assert!(wakeups <= 2 * steps + 1, "{wakeups} wake-ups");
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Per-frame timings written to a CSV file with `--timing-csv`, to plot them over a run.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+/// Rows kept in memory between writes to the file, about two seconds at 60 fps.
+const FLUSH_FRAMES: u32 = 120;
+
+/// Collects a row for every frame and writes them out in batches, so a slow disk
+/// doesn't hold up the frame that happens to write.
+pub struct TimingCsv {
+    file: BufWriter<File>,
+    rows: String,
+    buffered: u32,
+    frame: u64,
+}
+
+impl TimingCsv {
+    /// Create the file at `path` and write its header.
+    pub fn new(path: &Path) -> io::Result<TimingCsv> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "frame,generation,frame_ms,gpu_ms")?;
+        Ok(TimingCsv {
+            file,
+            rows: String::new(),
+            buffered: 0,
+            frame: 0,
+        })
+    }
+
+    /// Add a frame that took `frame_time` and showed `generation`. The GPU time is that
+    /// of the last measured compute pass, left empty when nothing new was measured.
+    pub fn add_frame(
+        &mut self,
+        generation: u64,
+        frame_time: Duration,
+        gpu_time: Option<Duration>,
+    ) -> io::Result<()> {
+        use std::fmt::Write;
+
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        // Writing into a `String` can't fail.
+        let _ = write!(
+            self.rows,
+            "{},{generation},{:.3},",
+            self.frame,
+            ms(frame_time)
+        );
+        if let Some(gpu_time) = gpu_time {
+            let _ = write!(self.rows, "{:.3}", ms(gpu_time));
+        }
+        self.rows.push('\n');
+        self.frame += 1;
+
+        self.buffered += 1;
+        if self.buffered >= FLUSH_FRAMES {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the rows collected so far to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.write_all(self.rows.as_bytes())?;
+        self.file.flush()?;
+        self.rows.clear();
+        self.buffered = 0;
+        Ok(())
+    }
+}
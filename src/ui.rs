@@ -0,0 +1,339 @@
+//! The egui control panel drawn on top of the grid.
+
+use std::{collections::VecDeque, time::Duration};
+
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use crate::{args::Args, keys};
+
+pub const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(1);
+pub const MAX_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+pub const MAX_STEPS_PER_TICK: u32 = 100;
+
+/// Values edited through the control panel.
+pub struct Controls {
+    pub paused: bool,
+    pub update_interval: Duration,
+    /// Generations run for each update interval, to fast-forward at a steady frame rate.
+    pub steps_per_tick: u32,
+    /// Probability of a cell being alive after a re-seed.
+    pub density: f64,
+    /// Set when the re-seed button was clicked.
+    pub reseed: bool,
+    /// Color of live cells, instead of the default position based colors.
+    pub alive_color: Option<[f32; 3]>,
+}
+
+impl Controls {
+    /// The values set on the command line, running.
+    pub fn new(args: &Args) -> Controls {
+        Controls {
+            paused: false,
+            update_interval: args.update_interval,
+            steps_per_tick: args.steps_per_tick,
+            density: args.density,
+            reseed: false,
+            alive_color: None,
+        }
+    }
+
+    /// Halve the update interval.
+    pub fn speed_up(&mut self) {
+        self.update_interval = (self.update_interval / 2).max(MIN_UPDATE_INTERVAL);
+        log::info!("Update interval {:?}", self.update_interval);
+    }
+
+    /// Double the update interval.
+    pub fn slow_down(&mut self) {
+        self.update_interval = (self.update_interval * 2).min(MAX_UPDATE_INTERVAL);
+        log::info!("Update interval {:?}", self.update_interval);
+    }
+
+    /// Double the generations per tick.
+    pub fn more_steps_per_tick(&mut self) {
+        self.steps_per_tick = (self.steps_per_tick * 2).min(MAX_STEPS_PER_TICK);
+        log::info!("{} generations per tick", self.steps_per_tick);
+    }
+
+    /// Halve the generations per tick.
+    pub fn fewer_steps_per_tick(&mut self) {
+        self.steps_per_tick = (self.steps_per_tick / 2).max(1);
+        log::info!("{} generations per tick", self.steps_per_tick);
+    }
+}
+
+fn panel(ctx: &egui::Context, controls: &mut Controls, hover: Option<&str>) {
+    egui::Window::new("Controls")
+        .resizable(false)
+        .show(ctx, |ui| {
+            let label = if controls.paused { "Play" } else { "Pause" };
+            if ui.button(label).clicked() {
+                controls.paused = !controls.paused;
+            }
+
+            let mut millis = controls.update_interval.as_millis() as u64;
+            let range =
+                MIN_UPDATE_INTERVAL.as_millis() as u64..=MAX_UPDATE_INTERVAL.as_millis() as u64;
+            ui.add(
+                egui::Slider::new(&mut millis, range)
+                    .logarithmic(true)
+                    .suffix(" ms")
+                    .text("Update interval"),
+            );
+            controls.update_interval = Duration::from_millis(millis);
+            ui.add(
+                egui::Slider::new(&mut controls.steps_per_tick, 1..=MAX_STEPS_PER_TICK)
+                    .logarithmic(true)
+                    .text("Steps per tick"),
+            );
+
+            ui.separator();
+            ui.add(egui::Slider::new(&mut controls.density, 0.0..=1.0).text("Density"));
+            controls.reseed |= ui.button("Re-seed").clicked();
+
+            ui.separator();
+            let mut custom = controls.alive_color.is_some();
+            ui.checkbox(&mut custom, "Custom cell color");
+            let mut color = controls.alive_color.unwrap_or([1.0; 3]);
+            if custom {
+                ui.color_edit_button_rgb(&mut color);
+            }
+            controls.alive_color = custom.then_some(color);
+
+            if let Some(hover) = hover {
+                ui.separator();
+                ui.label(hover);
+            }
+        });
+}
+
+/// Live cell counts of the most recent generations, toggled with the G key.
+#[derive(Default)]
+pub struct PopulationGraph {
+    pub visible: bool,
+    // Generation and population, oldest first.
+    history: VecDeque<[f64; 2]>,
+}
+
+impl PopulationGraph {
+    /// Number of samples kept.
+    const LENGTH: usize = 512;
+
+    /// Generation of the newest sample, if any.
+    pub fn last_generation(&self) -> Option<u64> {
+        self.history
+            .back()
+            .map(|&[generation, _]| generation as u64)
+    }
+
+    /// Add the population of `generation`, starting over when the grid was re-seeded.
+    pub fn push(&mut self, generation: u64, population: u64) {
+        if self.last_generation().is_some_and(|last| generation < last) {
+            self.history.clear();
+        }
+        if self.history.len() == Self::LENGTH {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back([generation as f64, population as f64]);
+    }
+
+    fn show(&self, ctx: &egui::Context) {
+        egui::Window::new("Population")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .resizable(false)
+            .show(ctx, |ui| {
+                let line = egui::plot::Line::new(
+                    self.history
+                        .iter()
+                        .copied()
+                        .collect::<egui::plot::PlotPoints>(),
+                );
+                egui::plot::Plot::new("population")
+                    .width(240.0)
+                    .height(120.0)
+                    .include_y(0.0)
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot| plot.line(line));
+            });
+    }
+}
+
+/// The controls from [`keys::BINDINGS`] over the dimmed grid, toggled with F1.
+fn help(ctx: &egui::Context) {
+    // Covers the other windows too, so clicks don't reach them.
+    let screen = ctx.screen_rect();
+    egui::Area::new("help background")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen.min)
+        .show(ctx, |ui| {
+            let (rect, _) = ui.allocate_exact_size(screen.size(), egui::Sense::click());
+            ui.painter()
+                .rect_filled(rect, 0.0, egui::Color32::from_black_alpha(180));
+        });
+
+    egui::Area::new("help")
+        .order(egui::Order::Tooltip)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.heading("Controls");
+                egui::Grid::new("help bindings")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mouse = keys::MOUSE.iter().copied();
+                        let bindings = keys::BINDINGS
+                            .iter()
+                            .map(|binding| (binding.label, binding.description));
+                        for (label, description) in bindings.chain(mouse) {
+                            ui.strong(label);
+                            ui.label(description);
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+}
+
+/// The egui context together with its winit input and wgpu rendering glue.
+pub struct Ui {
+    context: egui::Context,
+    input: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    population: PopulationGraph,
+    /// Description of the cell under the cursor.
+    pub hover: Option<String>,
+    help: bool,
+    /// Size of the surface the panel is drawn into.
+    screen_size: PhysicalSize<u32>,
+}
+
+impl Ui {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Ui {
+        let mut input = egui_winit::State::new(window);
+        input.set_pixels_per_point(window.scale_factor() as f32);
+        Ui {
+            context: egui::Context::default(),
+            input,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            population: PopulationGraph::default(),
+            hover: None,
+            help: false,
+            screen_size: window.inner_size(),
+        }
+    }
+
+    /// Lay the panel out for a surface of `size` from the next frame on.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.screen_size = size;
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.help = !self.help;
+    }
+
+    pub fn population_graph(&mut self) -> &mut PopulationGraph {
+        &mut self.population
+    }
+
+    /// Pass a window event to egui, returning whether egui used it. While the help is
+    /// shown it takes all input but F1 to close it. Releases still pass, so no key or
+    /// button is left held.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        let consumed = self.input.on_event(&self.context, event).consumed;
+        if !self.help {
+            return consumed;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode,
+                        ..
+                    },
+                ..
+            } => *virtual_keycode != Some(VirtualKeyCode::F1),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. } => true,
+            _ => consumed,
+        }
+    }
+
+    /// Run the panel for one frame and record drawing it into `view` on top of
+    /// what is already there.
+    pub fn draw(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        controls: &mut Controls,
+    ) {
+        let mut input = self.input.take_egui_input(window);
+        // The window can already have another size than the surface while it is resized,
+        // and the panel must stay inside the surface.
+        let size = self.screen_size;
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size.width as f32, size.height as f32) / self.input.pixels_per_point(),
+        ));
+        let graph = &self.population;
+        let hover = self.hover.as_deref();
+        let show_help = self.help;
+        let output = self.context.run(input, |ctx| {
+            panel(ctx, controls, hover);
+            if graph.visible {
+                graph.show(ctx);
+            }
+            if show_help {
+                help(ctx);
+            }
+        });
+        self.input
+            .handle_platform_output(window, &self.context, output.platform_output);
+
+        let screen = egui_wgpu::renderer::ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: self.context.pixels_per_point(),
+        };
+        let paint_jobs = self.context.tessellate(output.shapes);
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        // The returned command buffers are only used by paint callbacks, which the panel has none of.
+        self.renderer
+            .update_buffers(device, queue, encoder, &paint_jobs, &screen);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("egui"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.renderer.render(&mut pass, &paint_jobs, &screen);
+        drop(pass);
+
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Running in a browser canvas through WebGPU. Build for `wasm32-unknown-unknown` and
+//! generate the bindings next to `web/index.html`:
+//!
+//! ```sh
+//! cargo build --release --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --no-typescript --out-dir web/pkg \
+//!     target/wasm32-unknown-unknown/release/your-first-wgpu-app.wasm
+//! ```
+//!
+//! Then serve the `web` directory and open it in a browser with WebGPU.
+
+use winit::{event_loop::EventLoop, platform::web::WindowExtWebSys, window::WindowBuilder};
+
+use crate::{args::Args, run, simulation::SimBackend, TITLE};
+
+/// Start the app in a canvas appended to the page. There is no command line in the
+/// browser, so it runs with the default options.
+pub fn start() {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Info).expect("Failed to initialize the logger");
+
+    let mut args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => return log::error!("{err}"),
+    };
+    if let Some(flag) = reading_back(&args) {
+        return log::error!("'{flag}' isn't available in the browser");
+    }
+    args.seed.get_or_insert_with(rand::random);
+
+    let event_loop = EventLoop::new();
+    let window = match WindowBuilder::new()
+        .with_title(TITLE)
+        .with_inner_size(winit::dpi::LogicalSize::new(
+            args.window_width,
+            args.window_height,
+        ))
+        .build(&event_loop)
+    {
+        Ok(window) => window,
+        Err(err) => return log::error!("Failed to create the window: {err}"),
+    };
+    let body = web_sys::window()
+        .and_then(|page| page.document())
+        .and_then(|document| document.body());
+    let Some(body) = body else {
+        return log::error!("The page has no body to add the canvas to");
+    };
+    if let Err(err) = body.append_child(&web_sys::Element::from(window.canvas())) {
+        return log::error!("Failed to add the canvas to the page: {err:?}");
+    }
+
+    // The event loop takes over from here, so the future never returns on success.
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = run(event_loop, window, args, None).await {
+            log::error!("{err:#}");
+        }
+    });
+}
+
+/// The first of the options that read the grid back from the GPU and wait for it, which
+/// the browser can't: there buffers are only mapped once control returns to the page.
+fn reading_back(args: &Args) -> Option<&'static str> {
+    [
+        (args.follow, "--follow"),
+        (args.detect_cycles, "--detect-cycles"),
+        (args.sim_backend == SimBackend::Cpu, "--backend-sim cpu"),
+        (args.record.is_some(), "--record"),
+        (args.history > 0, "--history"),
+    ]
+    .into_iter()
+    .find_map(|(set, flag)| set.then_some(flag))
+}
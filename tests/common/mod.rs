@@ -0,0 +1,18 @@
+//! Setup shared by the tests that run on the GPU.
+
+use std::sync::Arc;
+
+use your_first_wgpu_app::gpu::{self, Backend};
+
+/// A device and queue from the first adapter found, or `None` on machines without one,
+/// such as CI runners, where the calling test returns early instead of failing.
+pub fn device() -> Option<(Arc<wgpu::Device>, Arc<wgpu::Queue>)> {
+    let instance = gpu::create_instance(Backend::All);
+    let Some(adapter) = pollster::block_on(instance.request_adapter(&Default::default())) else {
+        eprintln!("Skipping: no adapter available");
+        return None;
+    };
+    let (device, queue) = pollster::block_on(adapter.request_device(&Default::default(), None))
+        .expect("Failed to request a device");
+    Some((Arc::new(device), Arc::new(queue)))
+}
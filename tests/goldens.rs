@@ -0,0 +1,48 @@
+//! The golden runs still end in their snapshots in `tests/goldens`. Run with `BLESS=1` to
+//! overwrite the snapshots when a change in the result is intended.
+
+mod common;
+
+use your_first_wgpu_app::{
+    golden::{self, GOLDENS},
+    simulation::Simulation,
+    snapshot,
+};
+
+#[test]
+fn goldens_match_snapshots() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let bless = std::env::var_os("BLESS").is_some_and(|value| value == "1");
+    let mut failures = Vec::new();
+    for golden in &GOLDENS {
+        let settings = golden.settings();
+        let rule = golden.rule.parse().expect("Invalid golden rule");
+        let mut simulation = Simulation::new(device.clone(), queue.clone(), &settings, rule, None)
+            .expect("Failed to create the simulation");
+        simulation.advance(golden::GENERATIONS);
+        let cells = simulation.read_cells();
+
+        let path = golden.path();
+        let size = settings.grid_size;
+        if bless {
+            snapshot::write(&path, size.width as u32, size.height as u32, &cells)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {err}", path.display()));
+            continue;
+        }
+        let (_, _, expected) = snapshot::read(&path).unwrap_or_else(|err| {
+            panic!(
+                "Failed to read {}, run with BLESS=1 to create it: {err}",
+                path.display()
+            )
+        });
+        if cells != expected {
+            failures.push(golden.name);
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{failures:?} differ from their snapshots, run with BLESS=1 if the change is intended"
+    );
+}
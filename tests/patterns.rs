@@ -0,0 +1,56 @@
+//! Well known patterns evolve as expected on a torus, which catches off-by-one neighbor
+//! indexing and mixed up ping-pong buffers.
+
+mod common;
+
+use your_first_wgpu_app::{
+    pattern::Pattern,
+    rule::Rule,
+    simulation::{Boundary, Settings, Simulation},
+};
+
+/// Run the preset `name` for `generations` and check it ends up moved by `(dx, dy)`, with
+/// y pointing down as in the pattern.
+fn check(name: &str, generations: u32, (dx, dy): (usize, usize)) {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let settings = Settings {
+        boundary: Boundary::Wrap,
+        ..Settings::default()
+    };
+    let mut simulation = Simulation::new(device, queue, &settings, Rule::default(), None)
+        .expect("Failed to create the simulation");
+    let size = simulation.grid_size();
+    let pattern = Pattern::preset(name).expect("Unknown built-in pattern");
+    let start = pattern.place(size).expect("The pattern doesn't fit");
+    simulation.load_cells(&start);
+    simulation.advance(generations);
+
+    // Row 0 of the grid is at the bottom, so moving down is a lower row.
+    let (width, height) = (size.width, size.height);
+    let mut expected = vec![0; start.len()];
+    for (i, &cell) in start.iter().enumerate() {
+        let (col, row) = (i % width, i / width);
+        expected[(row + height - dy) % height * width + (col + dx) % width] = cell;
+    }
+    assert!(
+        simulation.read_cells() == expected,
+        "The {name} is wrong after {generations} generations"
+    );
+}
+
+#[test]
+fn block_is_still() {
+    check("block", 1, (0, 0));
+}
+
+#[test]
+fn blinker_has_period_two() {
+    check("blinker", 2, (0, 0));
+}
+
+#[test]
+fn glider_moves_diagonally() {
+    check("glider", 4, (1, 1));
+}
@@ -0,0 +1,54 @@
+//! A blinker read back with [`gpu::read_cell_state`] oscillates like the CPU reference.
+
+mod common;
+
+use your_first_wgpu_app::{
+    gpu,
+    pattern::Pattern,
+    reference,
+    rule::Rule,
+    simulation::{Settings, Simulation},
+};
+
+#[test]
+fn blinker_matches_reference() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let settings = Settings::default();
+    let mut simulation = Simulation::new(device, queue, &settings, Rule::default(), None)
+        .expect("Failed to create the simulation");
+    let size = simulation.grid_size();
+    let start = Pattern::preset("blinker")
+        .expect("Unknown built-in pattern")
+        .place(size)
+        .expect("The pattern doesn't fit");
+    simulation.load_cells(&start);
+
+    let len = (start.len() * std::mem::size_of::<u32>()) as u64;
+    let mut expected = start.clone();
+    for generation in 1..=4 {
+        simulation.advance(1);
+        expected = reference::step_cpu(
+            &expected,
+            size.width,
+            size.height,
+            Rule::default(),
+            settings.boundary,
+            settings.neighborhood,
+        );
+        let cells = pollster::block_on(gpu::read_cell_state(
+            simulation.device(),
+            simulation.queue(),
+            simulation.cell_state_storage(),
+            len,
+        ));
+        assert!(cells == expected, "Generation {generation} differs");
+        // Turned a quarter in odd generations, back where it started in even ones.
+        assert_eq!(
+            cells == start,
+            generation % 2 == 0,
+            "Generation {generation}"
+        );
+    }
+}
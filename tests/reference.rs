@@ -0,0 +1,75 @@
+//! One generation on the GPU matches one of the CPU reference.
+
+mod common;
+
+use your_first_wgpu_app::{
+    reference,
+    rule::Rule,
+    simulation::{Boundary, GridSize, Neighborhood, Settings, Simulation},
+};
+
+/// Seed a random grid of `grid_size` with `boundary`, step it once on the GPU and once
+/// with [`reference::step_cpu`], and check both agree on every cell. Returns the cells
+/// before and after, or `None` without an adapter.
+fn check(grid_size: GridSize, boundary: Boundary) -> Option<(Vec<u32>, Vec<u32>)> {
+    let (device, queue) = common::device()?;
+    let settings = Settings {
+        grid_size,
+        boundary,
+        seed: Some(1),
+        density: 0.5,
+        ..Settings::default()
+    };
+    let mut simulation = Simulation::new(device, queue, &settings, Rule::default(), None)
+        .expect("Failed to create the simulation");
+    let start = simulation.read_cells();
+    simulation.advance(1);
+
+    let expected = reference::step_cpu(
+        &start,
+        grid_size.width,
+        grid_size.height,
+        Rule::default(),
+        boundary,
+        Neighborhood::Moore,
+    );
+    let cells = simulation.read_cells();
+    if let Some(i) = (0..cells.len()).find(|&i| cells[i] != expected[i]) {
+        panic!(
+            "Cell ({}, {}) differs from the CPU reference with {boundary:?} edges: \
+             expected {}, got {}",
+            i % grid_size.width,
+            i / grid_size.width,
+            expected[i],
+            cells[i]
+        );
+    }
+    Some((start, cells))
+}
+
+#[test]
+fn wrap_matches_reference() {
+    check(GridSize::square(32), Boundary::Wrap);
+}
+
+#[test]
+fn dead_edges_match_reference() {
+    check(GridSize::square(32), Boundary::Dead);
+}
+
+#[test]
+fn edges_of_a_partial_workgroup_evolve() {
+    // With 8x8 workgroups the last 6 rows and columns are in workgroups that stick out of
+    // the grid, which were never dispatched when the count was rounded down.
+    let Some((start, cells)) = check(GridSize::square(30), Boundary::Wrap) else {
+        return;
+    };
+    let changed = (0..cells.len())
+        .filter(|&i| i % 30 >= 24 || i / 30 >= 24)
+        .filter(|&i| cells[i] != start[i])
+        .count();
+    assert!(
+        changed > 0,
+        "No cell beyond the first 24 rows and columns changed"
+    );
+}
@@ -0,0 +1,170 @@
+//! Drawing survives the render target changing size, including being minimized to 0x0
+//! and restored, as the window's surface does. The frames go to offscreen textures the
+//! size of the target, so no window or display is needed.
+
+mod common;
+
+use your_first_wgpu_app::{
+    gpu,
+    render_target::RenderTarget,
+    renderer::Renderer,
+    rule::Rule,
+    simulation::{Settings, Simulation},
+};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+// Samples per pixel, so the multisampled texture has to follow the size too.
+const SAMPLE_COUNT: u32 = 4;
+
+/// A simulation with every cell alive, so the cells cover much of any frame, a renderer
+/// for it and a 640x480 target, or `None` without an adapter.
+fn setup() -> Option<(Simulation, Renderer, RenderTarget)> {
+    let (device, queue) = common::device()?;
+    let mut simulation =
+        Simulation::new(device, queue, &Settings::default(), Rule::default(), None)
+            .expect("Failed to create the simulation");
+    simulation.fill(true);
+    let renderer = Renderer::new_multisampled(&simulation, FORMAT, SAMPLE_COUNT);
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: FORMAT,
+        width: 640,
+        height: 480,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: Vec::new(),
+    };
+    let target = RenderTarget::new(simulation.device(), config, SAMPLE_COUNT);
+    Some((simulation, renderer, target))
+}
+
+/// Draw the current generation into the multisampled texture of `target`, resolve it into
+/// a new texture the size of the target as into a frame of the surface, and read back how
+/// many of its pixels aren't the black background.
+fn render(simulation: &Simulation, renderer: &Renderer, target: &RenderTarget) -> usize {
+    let device = simulation.device();
+    let (width, height) = (target.config().width, target.config().height);
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Resize texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    // Rows in a texture to buffer copy must be aligned to 256 bytes.
+    let bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Resize readback"),
+        size: (bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    renderer.prepare(&mut encoder, simulation);
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.msaa().expect("No multisampled texture"),
+                resolve_target: Some(&view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        renderer.draw(&mut pass, simulation);
+    }
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    simulation.queue().submit(Some(encoder.finish()));
+
+    gpu::read_buffer(device, &buffer)
+        .chunks(bytes_per_row as usize)
+        .flat_map(|row| row[..(width * 4) as usize].chunks(4))
+        .filter(|pixel| pixel[..3] != [0, 0, 0])
+        .count()
+}
+
+/// Render as [`render`] does, checking that drawing into the target at its current size,
+/// and resolving into a frame of that size, hits no validation errors.
+fn render_checked(simulation: &Simulation, renderer: &Renderer, target: &RenderTarget) -> usize {
+    let device = simulation.device();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let lit = render(simulation, renderer, target);
+    let error = pollster::block_on(device.pop_error_scope());
+    assert!(error.is_none(), "Failed to render: {error:?}");
+    lit
+}
+
+#[test]
+fn minimizing_keeps_the_last_size() {
+    let Some((simulation, renderer, mut target)) = setup() else {
+        return;
+    };
+    let device = simulation.device();
+    assert!(render_checked(&simulation, &renderer, &target) > 0);
+
+    assert!(!target.resize(device, 0, 0), "Configured an empty surface");
+    assert!(target.is_minimized());
+    let config = target.config();
+    assert_eq!((config.width, config.height), (640, 480));
+
+    assert!(target.resize(device, 640, 480));
+    assert!(!target.is_minimized());
+    let lit = render_checked(&simulation, &renderer, &target);
+    assert!(lit > 0, "Nothing was drawn after restoring");
+}
+
+#[test]
+fn rendering_survives_resizing() {
+    const SIZES: [(u32, u32); 7] = [
+        (1, 1),
+        (1920, 1080),
+        (0, 480),
+        (333, 777),
+        (640, 0),
+        (333, 777),
+        (800, 600),
+    ];
+
+    let Some((simulation, renderer, mut target)) = setup() else {
+        return;
+    };
+    let mut lit = 0;
+    for (width, height) in SIZES {
+        let minimized = width == 0 || height == 0;
+        assert_eq!(
+            target.resize(simulation.device(), width, height),
+            !minimized
+        );
+        assert_eq!(target.is_minimized(), minimized);
+        // Nothing is drawn while minimized, as with the window.
+        if !minimized {
+            lit = render_checked(&simulation, &renderer, &target);
+        }
+    }
+    assert!(lit > 0, "Nothing was drawn after resizing");
+}